@@ -0,0 +1,207 @@
+//! Achievement definitions and the game events they are checked against.
+//!
+//! The definitions themselves live in an asset file, so adding an
+//! achievement doesn't need any code as long as its trigger already exists.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    profile::Ending,
+    rules::{Board, Merge},
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Achievements {
+    pub achievements: Vec<Achievement>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// Secret achievements are counted apart and give nothing away until
+    /// unlocked.
+    #[serde(default)]
+    pub hidden: bool,
+    pub trigger: Trigger,
+}
+
+/// Condition unlocking an achievement.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trigger {
+    /// A merge produced a smiler of at least `phase`, optionally only
+    /// counting normal or corrupted ones.
+    PhaseReached { phase: u8, corrupted: Option<bool> },
+    /// A normal and a corrupted smiler merged into a normal one.
+    LuckyMerge,
+    /// This many merges were made in a single game.
+    Merges(usize),
+    /// Every smiler on the board is corrupted.
+    AllCorrupted,
+    /// The game was won, optionally with a given ending and in at most
+    /// `within` merges.
+    EndingReached {
+        ending: Option<Ending>,
+        within: Option<usize>,
+    },
+}
+
+/// Something that happened in a game.
+#[derive(Clone, Copy, Debug)]
+pub enum GameEvent<'a> {
+    /// A merge was made; `moves` counts merges of the game including it.
+    Merged {
+        merge: Merge,
+        moves: usize,
+    },
+    BoardChanged(&'a Board),
+    EndingReached {
+        ending: Ending,
+        moves: usize,
+    },
+}
+
+impl Trigger {
+    pub fn is_met(&self, event: &GameEvent) -> bool {
+        match (self, event) {
+            (Trigger::PhaseReached { phase, corrupted }, GameEvent::Merged { merge, .. }) => {
+                merge.cell.phase >= *phase
+                    && corrupted.is_none_or(|corrupted| corrupted == merge.cell.corrupted)
+            }
+            (Trigger::LuckyMerge, GameEvent::Merged { merge, .. }) => {
+                merge.mixed && !merge.cell.corrupted
+            }
+            (Trigger::Merges(count), GameEvent::Merged { moves, .. }) => moves >= count,
+            (Trigger::AllCorrupted, GameEvent::BoardChanged(board)) => board.all_corrupted(),
+            (
+                Trigger::EndingReached { ending, within },
+                GameEvent::EndingReached {
+                    ending: reached,
+                    moves,
+                },
+            ) => {
+                ending.is_none_or(|ending| ending == *reached)
+                    && within.is_none_or(|within| *moves <= within)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Achievements {
+    /// Achievements whose trigger is met by `event`.
+    pub fn triggered_by<'a>(
+        &'a self,
+        event: &'a GameEvent,
+    ) -> impl Iterator<Item = &'a Achievement> + 'a {
+        self.achievements
+            .iter()
+            .filter(move |achievement| achievement.trigger.is_met(event))
+    }
+
+    /// Number of regular or secret achievements.
+    pub fn count(&self, hidden: bool) -> usize {
+        self.achievements
+            .iter()
+            .filter(|achievement| achievement.hidden == hidden)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::{Cell, Pos, Shape};
+
+    use super::*;
+
+    fn merged(phase: u8, corrupted: bool, mixed: bool, moves: usize) -> GameEvent<'static> {
+        GameEvent::Merged {
+            merge: Merge {
+                from: Pos::new(0, 0),
+                to: Pos::new(1, 0),
+                cell: Cell { phase, corrupted },
+                mixed,
+            },
+            moves,
+        }
+    }
+
+    fn ended(ending: Ending, moves: usize) -> GameEvent<'static> {
+        GameEvent::EndingReached { ending, moves }
+    }
+
+    #[test]
+    fn phase_reached() {
+        let any = Trigger::PhaseReached {
+            phase: 3,
+            corrupted: None,
+        };
+        assert!(!any.is_met(&merged(2, false, false, 1)));
+        assert!(any.is_met(&merged(3, false, false, 1)));
+        assert!(any.is_met(&merged(4, true, false, 1)));
+
+        let normal = Trigger::PhaseReached {
+            phase: 3,
+            corrupted: Some(false),
+        };
+        assert!(normal.is_met(&merged(3, false, false, 1)));
+        assert!(!normal.is_met(&merged(3, true, false, 1)));
+    }
+
+    #[test]
+    fn lucky_merge() {
+        assert!(Trigger::LuckyMerge.is_met(&merged(1, false, true, 1)));
+        assert!(!Trigger::LuckyMerge.is_met(&merged(1, true, true, 1)));
+        assert!(!Trigger::LuckyMerge.is_met(&merged(1, false, false, 1)));
+    }
+
+    #[test]
+    fn merges() {
+        assert!(!Trigger::Merges(10).is_met(&merged(1, false, false, 9)));
+        assert!(Trigger::Merges(10).is_met(&merged(1, false, false, 10)));
+    }
+
+    #[test]
+    fn all_corrupted() {
+        let mut board = Board::empty(Shape::rect(2, 1));
+        let corrupted = Cell {
+            phase: 0,
+            corrupted: true,
+        };
+        board.set(Pos::new(0, 0), Some(corrupted));
+        board.set(Pos::new(1, 0), Some(Cell::default()));
+        assert!(!Trigger::AllCorrupted.is_met(&GameEvent::BoardChanged(&board)));
+        board.set(Pos::new(1, 0), Some(corrupted));
+        assert!(Trigger::AllCorrupted.is_met(&GameEvent::BoardChanged(&board)));
+    }
+
+    #[test]
+    fn ending_reached() {
+        let any = Trigger::EndingReached {
+            ending: None,
+            within: None,
+        };
+        assert!(any.is_met(&ended(Ending::Normal, 50)));
+        assert!(any.is_met(&ended(Ending::Corrupted, 50)));
+
+        let fast_normal = Trigger::EndingReached {
+            ending: Some(Ending::Normal),
+            within: Some(20),
+        };
+        assert!(fast_normal.is_met(&ended(Ending::Normal, 20)));
+        assert!(!fast_normal.is_met(&ended(Ending::Normal, 21)));
+        assert!(!fast_normal.is_met(&ended(Ending::Corrupted, 10)));
+    }
+
+    #[test]
+    fn other_events_meet_nothing() {
+        assert!(!Trigger::Merges(1).is_met(&ended(Ending::Normal, 5)));
+        assert!(!Trigger::LuckyMerge.is_met(&ended(Ending::Normal, 5)));
+        let any_ending = Trigger::EndingReached {
+            ending: None,
+            within: None,
+        };
+        assert!(!any_ending.is_met(&merged(9, false, false, 5)));
+    }
+}
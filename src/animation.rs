@@ -0,0 +1,375 @@
+//! Smilers moving on the board: falls, merges and idle animations. The board
+//! only takes the next move once they're all done.
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use mergerration::rules::{Cell, Pos};
+use rand::Rng;
+
+use crate::{
+    board::{
+        cell_translation, spawn_smiler, Board, Corrupted, GridPos, Smiler, SmilerState,
+        CELL_INTERVAL, CELL_SIZE, SMILER_SCALE,
+    },
+    game::{GameInfo, GameRng, GameSet, SmilerMerged},
+    GameState,
+};
+
+/// Seconds a smiler takes to fall one cell. Longer falls take longer, but
+/// pick up speed on the way.
+const FALL_SECONDS: f32 = 0.2;
+/// Seconds a merged smiler takes to slide into its partner.
+const MERGE_SECONDS: f32 = 0.15;
+/// Seconds the partner takes to squash and spring back once hit.
+const SQUASH_SECONDS: f32 = 0.25;
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SmilerLanded>()
+            .add_event::<BoardSettled>()
+            .insert_resource(AnimationIndices {
+                normal_calm: Indices { first: 0, last: 21 },
+                normal_worried: Indices {
+                    first: 22,
+                    last: 43,
+                },
+                normal_scared: Indices {
+                    first: 44,
+                    last: 59,
+                },
+                corrupted_calm: Indices {
+                    first: 60,
+                    last: 79,
+                },
+                corrupted_happy: Indices {
+                    first: 80,
+                    last: 99,
+                },
+            })
+            .insert_resource(Settled(true))
+            .init_resource::<PendingRefills>()
+            .add_systems(
+                Update,
+                (
+                    (settle_board, start_falls, update_cells_position)
+                        .chain()
+                        .after(GameSet::Resolve),
+                    update_squashes,
+                    update_animation,
+                ),
+            );
+    }
+}
+
+/// Sends every smiler that isn't on its cell, or headed there, falling
+/// towards it.
+fn start_falls(
+    mut commands: Commands,
+    board: Res<Board>,
+    query: Query<(Entity, &Transform, &GridPos, Option<&Slide>), With<Smiler>>,
+) {
+    for (entity, transform, grid_pos, slide) in &query {
+        let from = transform.translation.truncate();
+        let to = cell_translation(&board, grid_pos.0);
+        if slide.map_or(from != to, |slide| slide.to != to) {
+            let cells = from.distance(to) / (CELL_SIZE + CELL_INTERVAL);
+            let seconds = FALL_SECONDS * cells.sqrt();
+            commands
+                .entity(entity)
+                .try_insert(Slide::new(from, to, seconds, ease_in));
+        }
+    }
+}
+
+/// Moves the sliding smilers along, all at once and at the same pace
+/// whatever the frame rate.
+fn update_cells_position(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &mut Transform,
+        &mut Slide,
+        Option<&GridPos>,
+        Has<MergedAway>,
+    )>,
+    mut landed: EventWriter<SmilerLanded>,
+) {
+    for (entity, mut transform, mut slide, grid_pos, merged_away) in &mut query {
+        slide.timer.tick(time.delta());
+        let progress = (slide.ease)(slide.timer.fraction());
+        let translation = slide.from.lerp(slide.to, progress);
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        if !slide.timer.finished() {
+            continue;
+        }
+        if merged_away {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        commands.entity(entity).remove::<Slide>();
+        if let Some(grid_pos) = grid_pos {
+            landed.send(SmilerLanded {
+                entity,
+                pos: grid_pos.0,
+            });
+        }
+    }
+}
+
+/// Squashes the smilers merged into and lets them spring back.
+fn update_squashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Transform, &mut Squash)>,
+) {
+    for (entity, mut transform, mut squash) in &mut query {
+        squash.0.tick(time.delta());
+        // It only gives once the merged smiler reaches it.
+        let elapsed = squash.0.elapsed_secs() - MERGE_SECONDS;
+        let amount = if elapsed > 0.0 {
+            0.25 * (PI * elapsed / SQUASH_SECONDS).sin()
+        } else {
+            0.0
+        };
+        transform.scale = Vec3::new(
+            SMILER_SCALE * (1.0 + amount),
+            SMILER_SCALE * (1.0 - amount),
+            1.0,
+        );
+        if squash.0.finished() {
+            transform.scale = Vec3::splat(SMILER_SCALE);
+            commands.entity(entity).remove::<Squash>();
+        }
+    }
+}
+
+/// Whether the board is done moving, so that it can take the next action.
+pub fn board_settled(settled: Res<Settled>) -> bool {
+    settled.0
+}
+
+fn ease_in(t: f32) -> f32 {
+    t * t
+}
+
+fn ease_in_out(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Once nothing moves anymore, drops the refills in, and when those have
+/// landed too, tells that the board settled.
+fn settle_board(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut board: ResMut<Board>,
+    mut rng: ResMut<GameRng>,
+    mut refills: ResMut<PendingRefills>,
+    sliding: Query<(), With<Slide>>,
+    mut settled: ResMut<Settled>,
+    mut settled_events: EventWriter<BoardSettled>,
+) {
+    if settled.0 || !sliding.is_empty() {
+        return;
+    }
+    if refills.0.is_empty() {
+        settled.0 = true;
+        settled_events.send(BoardSettled);
+        return;
+    }
+
+    // New smilers are stacked above their column and fall in from there.
+    let mut spawn_rows = vec![board.height(); board.width()];
+    for (pos, cell) in refills.0.drain(..) {
+        // Left over from a board laid out anew since.
+        if board.entity(pos).is_some() || board.get(pos) != Some(cell) {
+            continue;
+        }
+        let start = cell_translation(&board, Pos::new(pos.col, spawn_rows[pos.col]));
+        spawn_rows[pos.col] += 1;
+        let entity = spawn_smiler(
+            &mut commands,
+            &asset_server,
+            &mut texture_atlas_layouts,
+            &mut rng.animation,
+            cell,
+            pos,
+            start,
+        );
+        board.set_entity(pos, Some(entity));
+    }
+}
+
+/// Takes the smiler merged away off the board and slides it into its
+/// partner, which squashes under the hit.
+pub fn slide_merged(
+    mut commands: Commands,
+    mut merged: EventReader<SmilerMerged>,
+    mut board: ResMut<Board>,
+    mut settled: ResMut<Settled>,
+    mut query: Query<&mut Transform>,
+) {
+    for event in merged.read() {
+        settled.0 = false;
+        let to = cell_translation(&board, event.merge.to);
+        if let Some(entity) = board.entity(event.merge.from) {
+            let mut from = cell_translation(&board, event.merge.from);
+            if let Ok(mut transform) = query.get_mut(entity) {
+                from = transform.translation.truncate();
+                // Under its partner.
+                transform.translation.z = 0.9;
+            }
+            commands
+                .entity(entity)
+                .remove::<(Smiler, GridPos)>()
+                .try_insert((Slide::new(from, to, MERGE_SECONDS, ease_in_out), MergedAway));
+        }
+        if let Some(entity) = board.entity(event.merge.to) {
+            commands
+                .entity(entity)
+                .try_insert(Squash(Timer::from_seconds(
+                    MERGE_SECONDS + SQUASH_SECONDS,
+                    TimerMode::Once,
+                )));
+        }
+        board.set_entity(event.merge.from, None);
+    }
+}
+
+fn update_animation(
+    mut query: Query<(&mut Smiler, &Corrupted, &mut TextureAtlas)>,
+    indices: Res<AnimationIndices>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+    game_info: Res<GameInfo>,
+    state: Res<State<GameState>>,
+) {
+    for (mut smiler, corrupted, mut sprite) in &mut query {
+        smiler.animation_timer.tick(time.delta());
+        smiler.frame_timer.tick(time.delta());
+
+        if *state.get() == GameState::Ending {
+            smiler.state = match corrupted.0 {
+                true => {
+                    if game_info.current_win_corrupted {
+                        SmilerState::CorruptedHappy
+                    } else {
+                        SmilerState::CorruptedCalm
+                    }
+                }
+                false => {
+                    if game_info.current_win_corrupted {
+                        SmilerState::NormalScared
+                    } else {
+                        SmilerState::NormalCalm
+                    }
+                }
+            }
+        } else {
+            smiler.state = match (corrupted.0, smiler.corrupted_neighbors) {
+                (false, neighbors) if neighbors < 2 => SmilerState::NormalCalm,
+                (false, neighbors) if neighbors >= 4 => SmilerState::NormalScared,
+                (false, _) => SmilerState::NormalWorried,
+                (true, neighbors) if neighbors <= 4 => SmilerState::CorruptedCalm,
+                (true, _) => SmilerState::CorruptedHappy,
+            };
+        }
+
+        let current_state_indices = match smiler.state {
+            SmilerState::NormalCalm => &indices.normal_calm,
+            SmilerState::NormalWorried => &indices.normal_worried,
+            SmilerState::NormalScared => &indices.normal_scared,
+            SmilerState::CorruptedCalm => &indices.corrupted_calm,
+            SmilerState::CorruptedHappy => &indices.corrupted_happy,
+        };
+
+        if sprite.index >= current_state_indices.first && sprite.index <= current_state_indices.last
+        {
+            if sprite.index == current_state_indices.first {
+                if smiler.animation_timer.just_finished() {
+                    sprite.index += 1;
+                    smiler.frame_timer.reset();
+                }
+            } else if sprite.index == current_state_indices.last {
+                sprite.index = current_state_indices.first;
+                let timer = rng.animation.gen::<f32>() * 3.0;
+                smiler.animation_timer = Timer::from_seconds(timer, TimerMode::Once);
+            } else if smiler.frame_timer.just_finished() {
+                sprite.index += 1;
+                smiler.frame_timer.reset();
+            }
+        } else {
+            sprite.index = current_state_indices.first;
+            smiler.animation_timer =
+                Timer::from_seconds(rng.animation.gen::<f32>() * 3.0, TimerMode::Once);
+        }
+    }
+}
+
+/// Everything landed and the refills are in, the board is ready for the
+/// next move.
+#[derive(Event)]
+pub struct BoardSettled;
+
+/// Whether the board is done moving since the last merge. Merges, undo and
+/// the replay wait for it.
+#[derive(Resource)]
+pub struct Settled(pub bool);
+
+/// Smilers refilled on the board but not dropped in yet.
+#[derive(Resource, Default)]
+pub struct PendingRefills(pub Vec<(Pos, Cell)>);
+
+/// A falling smiler reached its cell.
+#[derive(Event)]
+pub struct SmilerLanded {
+    pub entity: Entity,
+    pub pos: Pos,
+}
+
+/// Eases an entity from one place to another over a set time.
+#[derive(Component)]
+struct Slide {
+    from: Vec2,
+    to: Vec2,
+    timer: Timer,
+    ease: fn(f32) -> f32,
+}
+
+impl Slide {
+    fn new(from: Vec2, to: Vec2, seconds: f32, ease: fn(f32) -> f32) -> Self {
+        Self {
+            from,
+            to,
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+            ease,
+        }
+    }
+}
+
+/// A smiler merged into another, gone once its slide there is over.
+#[derive(Component)]
+struct MergedAway;
+
+/// Squashes a smiler that another merged into.
+#[derive(Component)]
+struct Squash(Timer);
+
+#[derive(Resource)]
+struct AnimationIndices {
+    normal_calm: Indices,
+    normal_worried: Indices,
+    normal_scared: Indices,
+    corrupted_calm: Indices,
+    corrupted_happy: Indices,
+}
+
+struct Indices {
+    first: usize,
+    last: usize,
+}
@@ -0,0 +1,211 @@
+//! Game content read from asset files: the rules of free play, the campaign
+//! and its levels.
+
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use mergerration::{campaign, level, mode::Mode, rules::RuleSet};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    board::Board,
+    game::{GameSet, GameSetup, NewGame},
+};
+
+pub struct GameAssetsPlugin;
+
+impl Plugin for GameAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Level>()
+            .register_asset_loader(RonLoader::<Level>::new(&["level.ron"]))
+            .init_asset::<Rules>()
+            .register_asset_loader(RonLoader::<Rules>::new(&["rules.ron"]))
+            .init_asset::<Campaign>()
+            .register_asset_loader(RonLoader::<Campaign>::new(&["campaign.ron"]))
+            .init_resource::<FreePlayRules>()
+            .add_systems(Startup, (load_campaign, load_rules))
+            .add_systems(
+                Update,
+                (
+                    load_campaign_levels,
+                    (apply_rules, start_level).chain().in_set(GameSet::Setup),
+                ),
+            );
+    }
+}
+
+pub fn level_path(id: &str) -> String {
+    format!("levels/{id}.level.ron")
+}
+
+/// Starts the pending level once its file is loaded. A broken level falls
+/// back to the current setup.
+fn start_level(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    pending: Option<Res<PendingLevel>>,
+    levels: Res<Assets<Level>>,
+    mut setup: ResMut<GameSetup>,
+    mut new_games: EventWriter<NewGame>,
+) {
+    let Some(pending) = pending else {
+        return;
+    };
+    match levels.get(&pending.handle) {
+        Some(level) => match level.board() {
+            Ok(start) => {
+                *setup = GameSetup {
+                    level: Some(level.name.clone()),
+                    level_id: Some(pending.id.clone()),
+                    start,
+                    mode: Mode::Classic,
+                    move_limit: level.move_limit,
+                };
+            }
+            Err(error) => warn!("Ignoring level {:?}: {error}", pending.id),
+        },
+        None if asset_server.get_load_state(&pending.handle) == Some(LoadState::Failed) => {
+            warn!("Failed to load level {:?}", pending.id);
+        }
+        None => return,
+    }
+    commands.remove_resource::<PendingLevel>();
+    new_games.send(NewGame { seed: pending.seed });
+}
+
+fn load_rules(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(RulesHandle(asset_server.load("game.rules.ron")));
+}
+
+/// Plays free games by the rules file, including the one under way. With
+/// asset hot reloading on, edits to the file apply right away. The daily
+/// challenge sticks to the default rules so everyone plays the same game.
+fn apply_rules(
+    mut events: EventReader<AssetEvent<Rules>>,
+    handle: Res<RulesHandle>,
+    rules: Res<Assets<Rules>>,
+    mut free_play: ResMut<FreePlayRules>,
+    mut setup: ResMut<GameSetup>,
+    mut board: ResMut<Board>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event
+        else {
+            continue;
+        };
+        let Some(rules) = rules.get(id).filter(|_| id == handle.0.id()) else {
+            continue;
+        };
+        if let Err(error) = rules.validate() {
+            warn!("Ignoring the rules file: {error}");
+            continue;
+        }
+        free_play.0 = rules.0.clone();
+        if setup.level_id.is_none() && setup.mode != Mode::Daily {
+            setup.start.set_rules(rules.0.clone());
+            board.set_rules(rules.0.clone());
+        }
+    }
+}
+
+fn load_campaign(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CampaignHandle(asset_server.load("game.campaign.ron")));
+}
+
+/// Starts loading every campaign level once the campaign is known, so the
+/// level select can show their names.
+fn load_campaign_levels(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    campaign_handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+    loaded: Option<Res<CampaignLevels>>,
+) {
+    if loaded.is_some() {
+        return;
+    }
+    if let Some(campaign) = campaigns.get(&campaign_handle.0) {
+        let handles = campaign
+            .levels
+            .iter()
+            .map(|level| asset_server.load(level_path(&level.id)))
+            .collect();
+        commands.insert_resource(CampaignLevels(handles));
+    }
+}
+
+/// Loads any deserializable asset from a RON file.
+pub struct RonLoader<T> {
+    extensions: &'static [&'static str],
+    _asset: PhantomData<fn() -> T>,
+}
+
+impl<T> RonLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _asset: PhantomData,
+        }
+    }
+}
+
+impl<T: Asset + DeserializeOwned> AssetLoader for RonLoader<T> {
+    type Asset = T;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<T, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
+
+#[derive(Asset, TypePath, Deref, Deserialize)]
+#[serde(transparent)]
+pub struct Level(level::Level);
+
+/// Level to start as soon as it is loaded.
+#[derive(Resource)]
+pub struct PendingLevel {
+    pub id: String,
+    pub handle: Handle<Level>,
+    pub seed: u64,
+}
+
+#[derive(Asset, TypePath, Deref, Deserialize)]
+#[serde(transparent)]
+struct Rules(RuleSet);
+
+#[derive(Resource)]
+struct RulesHandle(Handle<Rules>);
+
+/// Rules free play games start with, from the rules file once it's loaded.
+#[derive(Resource, Default)]
+pub struct FreePlayRules(pub RuleSet);
+
+#[derive(Asset, TypePath, Deref, Deserialize)]
+#[serde(transparent)]
+pub struct Campaign(campaign::Campaign);
+
+#[derive(Resource)]
+pub struct CampaignHandle(pub Handle<Campaign>);
+
+/// Files of the campaign levels, in campaign order.
+#[derive(Resource)]
+pub struct CampaignLevels(pub Vec<Handle<Level>>);
@@ -0,0 +1,408 @@
+//! Headless balance simulator: plays many games by the game's own rules
+//! with a bot and reports how they went, one row per bot and refill
+//! corruption chance.
+//!
+//! ```text
+//! cargo run --release --bin simulate -- --games 2000 --strategy all \
+//!     --refill-corruption 0.5,0.6,0.7,0.8 --format csv > balance.csv
+//! ```
+//!
+//! Options:
+//! - `--games <n>`: games per row, 1000 by default.
+//! - `--strategy <random|greedy|solver>[,...]`: the bots to run, all of
+//!   them by default or with `all`. `solver` is much slower than the
+//!   others.
+//! - `--solver-games <n>`: games the solver plays out per merge it weighs,
+//!   20 by default.
+//! - `--board <width>x<height>`: board size, 4x4 by default.
+//! - `--level <name>`: play `levels/<name>.level.ron` instead, with its
+//!   board, refills, rules and move limit.
+//! - `--refill-corruption <chance>[,<chance>...]`: overrides the rules'
+//!   refill corruption chance, one row per value.
+//! - `--seed <n>`: seed of the first game, each next game adding one. Every
+//!   row plays the same seeds.
+//! - `--assets <dir>`: where the rules, levels and achievements are read
+//!   from, `assets` by default.
+//! - `--format <csv|json>`: output format, `csv` by default.
+
+use std::{collections::BTreeMap, fs, process::ExitCode};
+
+use mergerration::{
+    achievements::{Achievements, GameEvent},
+    hint,
+    level::Level,
+    profile::Ending,
+    rules::{self, Board, Pos, RuleSet, Shape},
+    solver::Solver,
+};
+use rand::{seq::IteratorRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+
+/// Merges after which a game that keeps going is given up on.
+const MAX_MOVES: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Strategy {
+    /// Any legal merge.
+    Random,
+    /// The merge the in-game hint suggests.
+    Greedy,
+    /// The solver's best merge.
+    Solver,
+}
+
+impl Strategy {
+    const ALL: [Strategy; 3] = [Strategy::Random, Strategy::Greedy, Strategy::Solver];
+
+    fn name(self) -> &'static str {
+        match self {
+            Strategy::Random => "random",
+            Strategy::Greedy => "greedy",
+            Strategy::Solver => "solver",
+        }
+    }
+
+    fn pick<R: Rng>(
+        self,
+        board: &Board,
+        move_limit: Option<usize>,
+        solver_games: usize,
+        rng: &mut R,
+    ) -> Option<(Pos, Pos)> {
+        match self {
+            Strategy::Random => board.merges().choose(rng),
+            Strategy::Greedy => hint::suggest(board),
+            Strategy::Solver => {
+                let mut solver = Solver::new(board, move_limit);
+                solver.run(solver_games * solver.moves().len(), rng);
+                solver.best().map(|stats| (stats.from, stats.to))
+            }
+        }
+    }
+}
+
+struct Options {
+    games: usize,
+    strategies: Vec<Strategy>,
+    solver_games: usize,
+    shape: Shape,
+    level: Option<String>,
+    refill_corruption: Vec<Option<f64>>,
+    seed: u64,
+    assets: String,
+    json: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            games: 1000,
+            strategies: Strategy::ALL.to_vec(),
+            solver_games: 20,
+            shape: Shape::rect(4, 4),
+            level: None,
+            refill_corruption: vec![None],
+            seed: 0,
+            assets: "assets".to_string(),
+            json: false,
+        };
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), value.to_string()),
+                None => {
+                    let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+                    (arg, value)
+                }
+            };
+            match flag.as_str() {
+                "--games" => options.games = parse_number(&flag, &value)?,
+                "--strategy" => {
+                    options.strategies = match value.as_str() {
+                        "all" => Strategy::ALL.to_vec(),
+                        _ => value
+                            .split(',')
+                            .map(|name| {
+                                Strategy::ALL
+                                    .into_iter()
+                                    .find(|strategy| strategy.name() == name.trim())
+                                    .ok_or_else(|| format!("unknown strategy {name:?}"))
+                            })
+                            .collect::<Result<_, _>>()?,
+                    }
+                }
+                "--solver-games" => options.solver_games = parse_number(&flag, &value)?,
+                "--board" => {
+                    let (width, height) = value
+                        .split_once('x')
+                        .ok_or_else(|| format!("expected <width>x<height>, got {value:?}"))?;
+                    options.shape =
+                        Shape::rect(parse_number(&flag, width)?, parse_number(&flag, height)?);
+                }
+                "--level" => options.level = Some(value),
+                "--refill-corruption" => {
+                    options.refill_corruption = value
+                        .split(',')
+                        .map(|chance| parse_number(&flag, chance).map(Some))
+                        .collect::<Result<_, _>>()?;
+                }
+                "--seed" => options.seed = parse_number(&flag, &value)?,
+                "--assets" => options.assets = value,
+                "--format" => {
+                    options.json = match value.as_str() {
+                        "csv" => false,
+                        "json" => true,
+                        _ => return Err(format!("unknown format {value:?}")),
+                    }
+                }
+                _ => return Err(format!("unknown option {flag}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("{flag}: {value:?} is not a valid number"))
+}
+
+fn read_ron<T: serde::de::DeserializeOwned>(path: &str) -> Result<T, String> {
+    let contents = fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+    ron::from_str(&contents).map_err(|error| format!("{path}: {error}"))
+}
+
+/// What a set of games came to.
+#[derive(Serialize)]
+struct Report {
+    strategy: Strategy,
+    refill_corruption: f64,
+    games: usize,
+    win_rate: f64,
+    /// Games won with a corrupted winning smiler.
+    corrupted_ending_rate: f64,
+    /// Games that ran out of merges, including levels out of moves.
+    stuck_rate: f64,
+    average_moves: f64,
+    average_moves_to_win: f64,
+    /// Share of games triggering each achievement, by id.
+    achievements: BTreeMap<String, f64>,
+}
+
+#[derive(Default)]
+struct Tally {
+    clean_wins: usize,
+    corrupted_wins: usize,
+    stuck: usize,
+    moves: usize,
+    moves_to_win: usize,
+    achievements: BTreeMap<String, usize>,
+}
+
+fn simulate(
+    options: &Options,
+    strategy: Strategy,
+    start: &Board,
+    move_limit: Option<usize>,
+    achievements: &Achievements,
+) -> Tally {
+    let mut tally = Tally::default();
+    for game in 0..options.games {
+        let mut rng = ChaCha8Rng::seed_from_u64(options.seed.wrapping_add(game as u64));
+        // The bot's own choices get their own stream so that every bot
+        // faces the same refills and corruption rolls for as long as it
+        // can.
+        let mut bot_rng = rng.clone();
+        bot_rng.set_stream(1);
+        let mut board = start.clone();
+        let mut triggered = Vec::new();
+        let mut record = |event: &GameEvent| {
+            for achievement in achievements.triggered_by(event) {
+                if !triggered.contains(&achievement.id) {
+                    triggered.push(achievement.id.clone());
+                }
+            }
+        };
+        record(&GameEvent::BoardChanged(&board));
+
+        let mut moves = 0;
+        let ending = loop {
+            let left = move_limit.map(|limit| limit.saturating_sub(moves));
+            if left == Some(0) || moves >= MAX_MOVES {
+                break None;
+            }
+            let Some((from, to)) = strategy.pick(&board, left, options.solver_games, &mut bot_rng)
+            else {
+                break None;
+            };
+            let Some(merge) = board.merge(from, to, &mut rng) else {
+                break None;
+            };
+            moves += 1;
+            record(&GameEvent::Merged { merge, moves });
+            // As in the game, the board is only checked once it's refilled,
+            // the winning move included.
+            board.apply_gravity();
+            board.refill(&mut rng);
+            record(&GameEvent::BoardChanged(&board));
+            if board.wins(&merge) {
+                let ending = Ending::new(merge.cell.corrupted);
+                // As in the game, only Pink Smilers reach endings.
+                if board.rules().win_phase == rules::WIN_PHASE {
+                    record(&GameEvent::EndingReached { ending, moves });
+                }
+                break Some(ending);
+            }
+        };
+
+        tally.moves += moves;
+        match ending {
+            Some(Ending::Normal) => tally.clean_wins += 1,
+            Some(Ending::Corrupted) => tally.corrupted_wins += 1,
+            None if moves < MAX_MOVES => tally.stuck += 1,
+            None => {}
+        }
+        if ending.is_some() {
+            tally.moves_to_win += moves;
+        }
+        for id in triggered {
+            *tally.achievements.entry(id).or_default() += 1;
+        }
+    }
+    tally
+}
+
+fn report(
+    options: &Options,
+    strategy: Strategy,
+    rules: &RuleSet,
+    tally: &Tally,
+    achievements: &Achievements,
+) -> Report {
+    let games = options.games.max(1) as f64;
+    let wins = tally.clean_wins + tally.corrupted_wins;
+    Report {
+        strategy,
+        refill_corruption: rules.refill_corruption,
+        games: options.games,
+        win_rate: wins as f64 / games,
+        corrupted_ending_rate: tally.corrupted_wins as f64 / games,
+        stuck_rate: tally.stuck as f64 / games,
+        average_moves: tally.moves as f64 / games,
+        average_moves_to_win: if wins == 0 {
+            0.0
+        } else {
+            tally.moves_to_win as f64 / wins as f64
+        },
+        achievements: achievements
+            .achievements
+            .iter()
+            .map(|achievement| {
+                let count = tally.achievements.get(&achievement.id).copied();
+                (achievement.id.clone(), count.unwrap_or(0) as f64 / games)
+            })
+            .collect(),
+    }
+}
+
+fn print_csv(reports: &[Report], achievements: &Achievements) {
+    let mut header = [
+        "strategy",
+        "refill_corruption",
+        "games",
+        "win_rate",
+        "corrupted_ending_rate",
+        "stuck_rate",
+        "average_moves",
+        "average_moves_to_win",
+    ]
+    .map(String::from)
+    .to_vec();
+    header.extend(
+        achievements
+            .achievements
+            .iter()
+            .map(|achievement| format!("achievement_{}", achievement.id)),
+    );
+    println!("{}", header.join(","));
+    for report in reports {
+        let mut row = vec![
+            report.strategy.name().to_string(),
+            report.refill_corruption.to_string(),
+            report.games.to_string(),
+            format!("{:.4}", report.win_rate),
+            format!("{:.4}", report.corrupted_ending_rate),
+            format!("{:.4}", report.stuck_rate),
+            format!("{:.2}", report.average_moves),
+            format!("{:.2}", report.average_moves_to_win),
+        ];
+        // Same order as the header, not the map's.
+        row.extend(achievements.achievements.iter().map(|achievement| {
+            let rate = report.achievements[&achievement.id];
+            format!("{rate:.4}")
+        }));
+        println!("{}", row.join(","));
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let assets = &options.assets;
+    let achievements: Achievements = read_ron(&format!("{assets}/game.achievements.ron"))?;
+    let (start, move_limit) = match &options.level {
+        Some(name) => {
+            let level: Level = read_ron(&format!("{assets}/levels/{name}.level.ron"))?;
+            let board = level.board().map_err(|error| format!("{name}: {error}"))?;
+            (board, level.move_limit)
+        }
+        None => {
+            let rules: RuleSet = read_ron(&format!("{assets}/game.rules.ron"))?;
+            rules.validate()?;
+            let mut board = Board::new(options.shape.clone());
+            board.set_rules(rules);
+            (board, None)
+        }
+    };
+
+    let mut reports = Vec::new();
+    for refill_corruption in &options.refill_corruption {
+        let mut start = start.clone();
+        let mut rules = start.rules().clone();
+        if let Some(chance) = refill_corruption {
+            rules.refill_corruption = *chance;
+            rules.validate()?;
+        }
+        start.set_rules(rules.clone());
+        for &strategy in &options.strategies {
+            eprintln!(
+                "Playing {} games with the {} bot at {} refill corruption",
+                options.games,
+                strategy.name(),
+                rules.refill_corruption
+            );
+            let tally = simulate(options, strategy, &start, move_limit, &achievements);
+            reports.push(report(options, strategy, &rules, &tally, &achievements));
+        }
+    }
+
+    if options.json {
+        let json = serde_json::to_string_pretty(&reports).map_err(|error| error.to_string())?;
+        println!("{json}");
+    } else {
+        print_csv(&reports, &achievements);
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = Options::parse(std::env::args().skip(1)).and_then(|options| run(&options));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("simulate: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! The board on screen: the smilers standing on the rules board and the
+//! empty cells under them.
+
+use bevy::prelude::*;
+use mergerration::rules::{self, Cell, Pos, Shape};
+use rand::Rng;
+
+use crate::{
+    game::{GameRng, DEFAULT_BOARD_SIZE},
+    save::load_game,
+};
+
+pub const CELL_SIZE: f32 = 125.0;
+pub const CELL_INTERVAL: f32 = 25.0;
+pub const SMILER_SCALE: f32 = 0.625;
+/// Where the board is centered in the world, left of the text.
+pub const BOARD_CENTER: Vec2 = Vec2::new(-135.0, 0.0);
+
+pub struct BoardPlugin;
+
+impl Plugin for BoardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Board::new(rules::Board::new(Shape::rect(
+            DEFAULT_BOARD_SIZE,
+            DEFAULT_BOARD_SIZE,
+        ))))
+        .insert_resource(SelectionSprite(None))
+        .add_systems(Startup, spawn_smilers.after(load_game))
+        .add_systems(Update, update_grid);
+    }
+}
+
+pub fn apply_gravity(mut board: ResMut<Board>, mut query: Query<&mut GridPos, With<Smiler>>) {
+    for fall in board.apply_gravity() {
+        let entity = board.entity(fall.from);
+        board.set_entity(fall.from, None);
+        board.set_entity(fall.to, entity);
+        if let Some(mut grid_pos) = entity.and_then(|entity| query.get_mut(entity).ok()) {
+            grid_pos.0 = fall.to;
+        }
+    }
+}
+
+pub fn spawn_smiler(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    texture_atlas_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
+    animation_rng: &mut impl Rng,
+    cell: Cell,
+    pos: Pos,
+    start: Vec2,
+) -> Entity {
+    let texture_expr = asset_server.load("expressions.png");
+    let layout_expr = TextureAtlasLayout::from_grid(Vec2::new(200.0, 200.0), 10, 10, None, None);
+    let texture_atlas_layout_expr = texture_atlas_layouts.add(layout_expr);
+
+    let texture_color = asset_server.load("colors.png");
+    let layout_color = TextureAtlasLayout::from_grid(Vec2::new(200.0, 200.0), 3, 2, None, None);
+    let texture_atlas_layout_color = texture_atlas_layouts.add(layout_color);
+
+    commands
+        .spawn((
+            SpriteSheetBundle {
+                texture: texture_expr.clone(),
+                atlas: TextureAtlas {
+                    layout: texture_atlas_layout_expr.clone(),
+                    index: if cell.corrupted { 1 } else { 0 },
+                },
+                transform: Transform::from_xyz(start.x, start.y, 1.0)
+                    .with_scale(Vec3::splat(SMILER_SCALE)),
+                ..default()
+            },
+            Smiler {
+                corrupted_neighbors: 0,
+                state: if cell.corrupted {
+                    SmilerState::CorruptedCalm
+                } else {
+                    SmilerState::NormalCalm
+                },
+                animation_timer: Timer::from_seconds(
+                    animation_rng.gen::<f32>() * 3.0,
+                    TimerMode::Once,
+                ),
+                frame_timer: Timer::from_seconds(0.05, TimerMode::Once),
+            },
+            Corrupted(cell.corrupted),
+            GridPos(pos),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                SpriteSheetBundle {
+                    texture: texture_color.clone(),
+                    atlas: TextureAtlas {
+                        layout: texture_atlas_layout_color.clone(),
+                        index: color_index(cell),
+                    },
+                    transform: Transform::from_xyz(0.0, 0.0, -5.0),
+                    ..default()
+                },
+                SmilerColor,
+            ));
+        })
+        .id()
+}
+
+/// Lays out the empty cells under the smilers whenever the board shape
+/// changes.
+fn update_grid(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    board: Res<Board>,
+    cells: Query<Entity, With<GridCell>>,
+    mut shown: Local<Option<Shape>>,
+) {
+    if shown.as_ref() == Some(board.shape()) {
+        return;
+    }
+    for entity in &cells {
+        commands.entity(entity).despawn();
+    }
+    for pos in board.positions() {
+        let coords = cell_translation(&board, pos);
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("cell.png"),
+                transform: Transform::from_xyz(coords.x, coords.y, -10.0)
+                    .with_scale(Vec3::splat(SMILER_SCALE)),
+                ..default()
+            },
+            GridCell,
+        ));
+    }
+    *shown = Some(board.shape().clone());
+}
+
+pub fn spawn_smilers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut board: ResMut<Board>,
+    mut rng: ResMut<GameRng>,
+) {
+    for pos in board.positions().collect::<Vec<_>>() {
+        if let Some(cell) = board.get(pos) {
+            let entity = spawn_smiler(
+                &mut commands,
+                &asset_server,
+                &mut texture_atlas_layouts,
+                &mut rng.animation,
+                cell,
+                pos,
+                cell_translation(&board, pos),
+            );
+            board.set_entity(pos, Some(entity));
+        }
+    }
+}
+
+/// Mirrors the rules board onto the smiler entities standing on it.
+pub fn update_smilers(
+    board: Res<Board>,
+    mut smilers: Query<(&GridPos, &mut Smiler, &mut Corrupted, &Children)>,
+    mut colors: Query<&mut TextureAtlas, With<SmilerColor>>,
+) {
+    for (pos, mut smiler, mut corrupted, children) in &mut smilers {
+        let Some(cell) = board.get(pos.0) else {
+            continue;
+        };
+        corrupted.0 = cell.corrupted;
+        smiler.corrupted_neighbors = board.corrupted_neighbors(pos.0);
+        for child in children {
+            if let Ok(mut color_sprite) = colors.get_mut(*child) {
+                color_sprite.index = color_index(cell);
+            }
+        }
+    }
+}
+
+fn color_index(cell: Cell) -> usize {
+    cell.phase.min(rules::WIN_PHASE) as usize
+}
+
+pub fn cell_translation(board: &rules::Board, pos: Pos) -> Vec2 {
+    let size = Vec2::new(board.width() as f32, board.height() as f32);
+    let offset = Vec2::new(pos.col as f32, pos.row as f32) - (size - 1.0) / 2.0;
+    BOARD_CENTER + offset * (CELL_SIZE + CELL_INTERVAL)
+}
+
+/// Cell under the given world coordinates, if the point lies on a smiler.
+pub fn cell_at(board: &rules::Board, coords: Vec2) -> Option<Pos> {
+    let origin = cell_translation(board, Pos::new(0, 0));
+    let step = CELL_SIZE + CELL_INTERVAL;
+    let col = ((coords.x - origin.x) / step).round();
+    let row = ((coords.y - origin.y) / step).round();
+    if col < 0.0 || row < 0.0 {
+        return None;
+    }
+    let pos = Pos::new(col as usize, row as usize);
+    let center = cell_translation(board, pos);
+    if board.contains(pos)
+        && (center.x - coords.x).abs() < 50.0
+        && (center.y - coords.y).abs() < 50.0
+    {
+        Some(pos)
+    } else {
+        None
+    }
+}
+
+#[derive(Component)]
+pub struct SmilerColor;
+
+#[derive(Component)]
+pub struct Corrupted(pub bool);
+
+#[derive(Component)]
+pub struct GridPos(pub Pos);
+
+/// Empty cell sprite under the smilers.
+#[derive(Component)]
+struct GridCell;
+
+/// Rules board plus the smiler entity standing in each of its cells.
+#[derive(Resource, Deref, DerefMut)]
+pub struct Board {
+    #[deref]
+    pub state: rules::Board,
+    entities: Vec<Option<Entity>>,
+}
+
+impl Board {
+    fn new(state: rules::Board) -> Self {
+        Self {
+            entities: vec![None; state.width() * state.height()],
+            state,
+        }
+    }
+
+    pub fn reset(&mut self, state: rules::Board) {
+        *self = Self::new(state);
+    }
+
+    pub fn entity(&self, pos: Pos) -> Option<Entity> {
+        if self.contains(pos) {
+            self.entities[pos.row * self.width() + pos.col]
+        } else {
+            None
+        }
+    }
+
+    pub fn set_entity(&mut self, pos: Pos, entity: Option<Entity>) {
+        if self.contains(pos) {
+            let width = self.width();
+            self.entities[pos.row * width + pos.col] = entity;
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct SelectionSprite(pub Option<Entity>);
+
+#[derive(Component)]
+pub enum SmilerState {
+    NormalCalm,
+    NormalWorried,
+    NormalScared,
+    CorruptedCalm,
+    CorruptedHappy,
+}
+
+#[derive(Component)]
+pub struct Smiler {
+    pub corrupted_neighbors: usize,
+    pub state: SmilerState,
+    pub animation_timer: Timer,
+    pub frame_timer: Timer,
+}
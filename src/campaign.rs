@@ -0,0 +1,55 @@
+//! The campaign: levels played in order, each unlocked by finishing the one
+//! before it, with up to three stars to earn on each.
+
+use serde::Deserialize;
+
+use crate::profile::Profile;
+
+pub const MAX_STARS: u8 = 3;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Campaign {
+    pub levels: Vec<CampaignLevel>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CampaignLevel {
+    /// File name of the level in `levels/`, without `.level.ron`.
+    pub id: String,
+    /// Merges to finish within for the third star.
+    #[serde(default)]
+    pub par: Option<usize>,
+}
+
+impl CampaignLevel {
+    /// One star for finishing, one for a normal ending and one for
+    /// finishing within par.
+    pub fn stars(&self, corrupted: bool, moves: usize) -> u8 {
+        1 + u8::from(!corrupted) + u8::from(self.par.is_some_and(|par| moves <= par))
+    }
+}
+
+impl Campaign {
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.levels.iter().position(|level| level.id == id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CampaignLevel> {
+        self.levels.iter().find(|level| level.id == id)
+    }
+
+    /// Level coming after `id`, if any.
+    pub fn next(&self, id: &str) -> Option<&CampaignLevel> {
+        self.levels.get(self.position(id)? + 1)
+    }
+
+    /// The first level is always open, the others once the previous one has
+    /// been finished.
+    pub fn is_unlocked(&self, index: usize, profile: &Profile) -> bool {
+        index == 0
+            || self
+                .levels
+                .get(index - 1)
+                .is_some_and(|level| profile.stars(&level.id) > 0)
+    }
+}
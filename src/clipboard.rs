@@ -0,0 +1,83 @@
+//! Copying text for the player to paste elsewhere: through the system's
+//! clipboard tool on desktop, the clipboard API on the web.
+
+use std::io;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn copy(text: &str) -> io::Result<()> {
+    let tools: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    };
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no clipboard tool");
+    for (program, args) in tools {
+        match pipe_to(program, args, text) {
+            Ok(()) => return Ok(()),
+            Err(error) => last_error = error,
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn pipe_to(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    use std::{
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(windows)]
+    {
+        // Keep a console window from flashing up.
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let mut child = command.spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(text.as_bytes())?;
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{program} failed")))
+    }
+}
+
+/// Starts the copy; the browser finishes it in the background.
+#[cfg(target_arch = "wasm32")]
+pub fn copy(text: &str) -> io::Result<()> {
+    use web_sys::{
+        js_sys::{Function, Reflect},
+        wasm_bindgen::{JsCast, JsValue},
+    };
+
+    let unsupported = || io::Error::new(io::ErrorKind::Unsupported, "no clipboard");
+    let navigator = web_sys::window().ok_or_else(unsupported)?.navigator();
+    // `navigator.clipboard` is still behind an unstable flag in web-sys.
+    let clipboard =
+        Reflect::get(&navigator, &JsValue::from_str("clipboard")).map_err(|_| unsupported())?;
+    let write_text: Function = Reflect::get(&clipboard, &JsValue::from_str("writeText"))
+        .ok()
+        .and_then(|write_text| write_text.dyn_into().ok())
+        .ok_or_else(unsupported)?;
+    write_text
+        .call1(&clipboard, &JsValue::from_str(text))
+        .map_err(|error| io::Error::other(format!("{error:?}")))?;
+    Ok(())
+}
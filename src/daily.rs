@@ -0,0 +1,79 @@
+//! Daily challenge: one seed for everyone playing on the same UTC day, and
+//! a result to share.
+
+use crate::profile::Ending;
+
+/// Seed of the daily challenge at unix time `time`, the date as `YYYYMMDD`
+/// so it reads like one.
+pub fn seed(time: u64) -> u64 {
+    let (year, month, day) = civil_date(time);
+    year.max(0) as u64 * 10_000 + u64::from(month) * 100 + u64::from(day)
+}
+
+/// Result of the daily challenge of `seed` in a few lines to paste in a chat.
+/// `ending` is `None` for a game that ran out of merges.
+pub fn share_text(seed: u64, ending: Option<Ending>, moves: usize, score: u32) -> String {
+    let date = format!(
+        "{:04}-{:02}-{:02}",
+        seed / 10_000,
+        seed / 100 % 100,
+        seed % 100
+    );
+    let outcome = match ending {
+        Some(Ending::Normal) => format!("Built a Pink Smiler in {moves} moves"),
+        Some(Ending::Corrupted) => format!("Built a corrupted Pink Smiler in {moves} moves"),
+        None => format!("Ran out of merges after {moves} moves"),
+    };
+    format!("Mergerration daily challenge {date}\n{outcome}\nScore: {score}")
+}
+
+/// Year, month and day of unix time `time`, in UTC.
+pub fn civil_date(time: u64) -> (i64, u32, u32) {
+    // Days to civil date, from Howard Hinnant's date algorithms.
+    let days = (time / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(951_782_400), (2000, 2, 29));
+        assert_eq!(civil_date(1_767_225_599), (2025, 12, 31));
+        assert_eq!(civil_date(1_767_225_600), (2026, 1, 1));
+    }
+
+    #[test]
+    fn seed_is_the_utc_day() {
+        assert_eq!(seed(1_709_164_800), 20_240_229);
+        assert_eq!(seed(1_709_251_199), 20_240_229);
+        assert_eq!(seed(1_709_251_200), 20_240_301);
+    }
+
+    #[test]
+    fn share_text_names_the_day() {
+        let text = share_text(20_240_229, Some(Ending::Corrupted), 42, 1234);
+        assert_eq!(
+            text,
+            "Mergerration daily challenge 2024-02-29\n\
+             Built a corrupted Pink Smiler in 42 moves\n\
+             Score: 1234"
+        );
+    }
+}
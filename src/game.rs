@@ -0,0 +1,775 @@
+//! The game being played: how it starts, the moves made on its board, and
+//! how it ends.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use mergerration::{
+    daily,
+    history::{History, Move, Snapshot},
+    mode::Mode,
+    profile::{unix_time, Ending},
+    replay,
+    rules::{self, Cell, Merge, Pos, RuleSet, Selection, Shape},
+};
+use rand::{random, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::{board_settled, slide_merged, BoardSettled, PendingRefills, Settled, SmilerLanded},
+    assets::{level_path, Campaign, CampaignHandle, FreePlayRules, PendingLevel},
+    board::{
+        apply_gravity, cell_translation, spawn_smilers, update_smilers, Board, SelectionSprite,
+        Smiler, SMILER_SCALE,
+    },
+    launch_option,
+    progress::PlayerProfile,
+    recording::{play_replay, ReplayPlayer},
+    scoreboard::{submit_score, HighScoreTable, ScoreEntry},
+    ui::{spawn_toast, ToastArea},
+    GameState,
+};
+
+pub const DEFAULT_BOARD_SIZE: usize = 4;
+const MIN_BOARD_SIZE: usize = 3;
+const MAX_BOARD_SIZE: usize = 8;
+
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SmilerMerged>()
+            .add_event::<SmilerCorrupted>()
+            .add_event::<SmilerSpawned>()
+            .add_event::<GameWon>()
+            .add_event::<SmilerCashedIn>()
+            .add_event::<BoardAction>()
+            .add_event::<NewGame>()
+            .insert_resource(GameSetup::from_shape(
+                Shape::rect(DEFAULT_BOARD_SIZE, DEFAULT_BOARD_SIZE),
+                RuleSet::default(),
+                Mode::Classic,
+            ))
+            .init_resource::<MoveLog>()
+            .init_resource::<ActionQueue>()
+            .insert_resource(GameRng::new(
+                launch_option("seed")
+                    .and_then(|seed| seed.parse().ok())
+                    .unwrap_or_else(new_seed),
+            ))
+            .init_resource::<GameInfo>()
+            .configure_sets(
+                Update,
+                (
+                    GameSet::Input,
+                    GameSet::Act,
+                    GameSet::Resolve,
+                    GameSet::Outcome,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .configure_sets(
+                Update,
+                (GameSet::Request, GameSet::Setup, GameSet::Start).chain(),
+            )
+            .add_systems(Startup, configure_board)
+            .add_systems(OnExit(GameState::Playing), clear_actions)
+            .add_systems(
+                Update,
+                (
+                    (
+                        queue_actions,
+                        (undo_redo, apply_board_actions).run_if(board_settled),
+                    )
+                        .chain()
+                        .in_set(GameSet::Act),
+                    // The board's entities follow the rules board step by
+                    // step, so the other plugins' part of a move is laid out
+                    // here too.
+                    (
+                        (slide_merged, detect_win),
+                        cash_in,
+                        apply_gravity,
+                        spawn_new_cells,
+                        update_smilers,
+                    )
+                        .chain()
+                        .in_set(GameSet::Resolve),
+                    (
+                        log_game_events,
+                        // The more specific endings win over a deadlock.
+                        (detect_deadlock, detect_out_of_moves, end_game).chain(),
+                    )
+                        .in_set(GameSet::Outcome),
+                    count_down.run_if(in_state(GameState::Playing)),
+                    shuffle_board
+                        .after(play_replay)
+                        .run_if(in_state(GameState::GameOver)),
+                    start_new_game.in_set(GameSet::Start),
+                ),
+            );
+    }
+}
+
+/// Steps of a frame the plugins order their systems by. The first four make
+/// up a turn and only run while playing, the others start new games.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    /// The player's input turned into board actions.
+    Input,
+    /// Queued board actions played, each waiting for the board to settle.
+    Act,
+    /// What a merge leads to: wins, cash-ins, gravity and refills.
+    Resolve,
+    /// Statistics, achievements and the end of the game.
+    Outcome,
+    /// Buttons and keys asking for another game.
+    Request,
+    /// The setup of the next game decided, levels once they're loaded.
+    Setup,
+    /// The board laid out anew for the next game.
+    Start,
+}
+
+/// Reads the board shape from `--board <width>x<height>`, or from
+/// `--mask <rows>` for other shapes: rows from top to bottom separated by
+/// `/`, `#` for a cell and `.` for a hole, e.g. `--mask ##../####/####`.
+/// `--level <name>` plays `assets/levels/<name>.level.ron` instead.
+pub fn configure_board(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    rng: Res<GameRng>,
+    rules: Res<FreePlayRules>,
+    mut setup: ResMut<GameSetup>,
+    mut board: ResMut<Board>,
+) {
+    if let Some(name) = launch_option("level") {
+        commands.insert_resource(PendingLevel {
+            handle: asset_server.load(level_path(&name)),
+            id: name,
+            seed: rng.seed,
+        });
+    } else if let Some(mask) = launch_option("mask") {
+        match Shape::from_rows(&mask.split('/').collect::<Vec<_>>()) {
+            Ok(shape) => *setup = GameSetup::from_shape(shape, rules.0.clone(), setup.mode),
+            Err(error) => warn!("Ignoring board mask: {error}"),
+        }
+    } else if let Some(size) = launch_option("board") {
+        let parsed = size.split_once('x').and_then(|(width, height)| {
+            Some((width.parse::<usize>().ok()?, height.parse::<usize>().ok()?))
+        });
+        match parsed {
+            Some((width, height)) => {
+                let shape = Shape::rect(
+                    width.clamp(MIN_BOARD_SIZE, MAX_BOARD_SIZE),
+                    height.clamp(MIN_BOARD_SIZE, MAX_BOARD_SIZE),
+                );
+                *setup = GameSetup::from_shape(shape, rules.0.clone(), setup.mode);
+            }
+            None => warn!("Ignoring board size {size:?}, expected <width>x<height>"),
+        }
+    }
+    board.reset(setup.start.clone());
+}
+
+/// Refills the board right after a move, the new smilers only drop in once
+/// the others have landed.
+fn spawn_new_cells(
+    mut board: ResMut<Board>,
+    mut rng: ResMut<GameRng>,
+    mut move_log: ResMut<MoveLog>,
+    mut refills: ResMut<PendingRefills>,
+    mut settled: ResMut<Settled>,
+    mut spawned_events: EventWriter<SmilerSpawned>,
+    setup: Res<GameSetup>,
+) {
+    let spawned = board.refill(&mut rng.board);
+
+    // Finding nothing pending must not count as a change, autosave watches
+    // the log.
+    if let Some((merge, before)) = move_log.bypass_change_detection().pending.take() {
+        move_log.record(Move {
+            merge,
+            refill: spawned.clone(),
+            before,
+            after: Snapshot {
+                board: board.state.clone(),
+                rng: rng.board.clone(),
+            },
+        });
+        // Games cashing in can't be undone, keeping every state would only
+        // make the save grow.
+        if setup.mode.cashes_in() {
+            move_log.forget(0);
+        }
+    }
+
+    for &(pos, cell) in &spawned {
+        spawned_events.send(SmilerSpawned { pos, cell });
+    }
+    // Refills outside a merge, like a level's spawn row, have to drop in
+    // too.
+    if !spawned.is_empty() {
+        settled.0 = false;
+    }
+    refills.0.extend(spawned);
+}
+
+/// Queues up the player's actions, so that each waits for the board to
+/// settle from the one before.
+fn queue_actions(
+    mut actions: EventReader<BoardAction>,
+    mut new_games: EventReader<NewGame>,
+    mut queue: ResMut<ActionQueue>,
+) {
+    if new_games.read().count() > 0 {
+        queue.0.clear();
+    }
+    // The shuffle only comes up once the game is over.
+    let actions = actions
+        .read()
+        .filter(|action| !matches!(action, BoardAction::Shuffle));
+    queue.0.extend(actions);
+}
+
+/// Whatever was queued has no board to play on anymore.
+fn clear_actions(mut queue: ResMut<ActionQueue>) {
+    queue.0.clear();
+}
+
+/// Plays the queued actions up to the first merge, the rest wait for the
+/// board to settle.
+fn apply_board_actions(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut queue: ResMut<ActionQueue>,
+    mut selection_sprite: ResMut<SelectionSprite>,
+    mut board: ResMut<Board>,
+    mut rng: ResMut<GameRng>,
+    mut move_log: ResMut<MoveLog>,
+    mut merged: EventWriter<SmilerMerged>,
+    mut corrupted: EventWriter<SmilerCorrupted>,
+) {
+    while let Some(&action) = queue.0.front() {
+        // Left for `undo_redo`.
+        if matches!(action, BoardAction::Undo | BoardAction::Redo) {
+            break;
+        }
+        queue.0.pop_front();
+        let before = Snapshot {
+            board: board.state.clone(),
+            rng: rng.board.clone(),
+        };
+        let selection = match action {
+            BoardAction::Select(pos) => board.select(pos, &mut rng.board),
+            BoardAction::Merge { from, to } => board
+                .merge(from, to, &mut rng.board)
+                .map_or(Selection::Ignored, Selection::Merged),
+            BoardAction::Deselect => {
+                board.deselect();
+                if let Some(sprite) = selection_sprite.0.take() {
+                    commands.entity(sprite).despawn();
+                }
+                continue;
+            }
+            // Handled by `undo_redo` and `shuffle_board`.
+            BoardAction::Undo | BoardAction::Redo | BoardAction::Shuffle => continue,
+        };
+        match selection {
+            Selection::Selected(pos) => {
+                let coords = cell_translation(&board, pos);
+                let sprite = commands
+                    .spawn(SpriteBundle {
+                        texture: asset_server.load("selection.png"),
+                        transform: Transform::from_xyz(coords.x, coords.y, 1.0)
+                            .with_scale(Vec3::splat(SMILER_SCALE)),
+                        ..default()
+                    })
+                    .id();
+                selection_sprite.0 = Some(sprite);
+            }
+            Selection::Deselected(_) => {
+                if let Some(sprite) = selection_sprite.0.take() {
+                    commands.entity(sprite).despawn();
+                }
+            }
+            Selection::Merged(merge) => {
+                if let Some(sprite) = selection_sprite.0.take() {
+                    commands.entity(sprite).despawn();
+                }
+                move_log.pending = Some((merge, before));
+                merged.send(SmilerMerged {
+                    merge,
+                    moves: move_log.merges().len() + 1,
+                });
+                if merge.mixed && merge.cell.corrupted {
+                    corrupted.send(SmilerCorrupted { pos: merge.to });
+                }
+                break;
+            }
+            Selection::Ignored => {}
+        }
+    }
+}
+
+fn detect_win(
+    board: Res<Board>,
+    setup: Res<GameSetup>,
+    mut merged: EventReader<SmilerMerged>,
+    mut won: EventWriter<GameWon>,
+    mut cashed_in: EventWriter<SmilerCashedIn>,
+) {
+    for event in merged.read() {
+        if !board.wins(&event.merge) {
+            continue;
+        }
+        if setup.mode.cashes_in() && !event.merge.cell.corrupted {
+            cashed_in.send(SmilerCashedIn {
+                pos: event.merge.to,
+            });
+        } else {
+            won.send(GameWon {
+                corrupted: event.merge.cell.corrupted,
+                moves: event.moves,
+                pink: board.rules().win_phase == rules::WIN_PHASE,
+            });
+        }
+    }
+}
+
+/// Takes cashed in smilers off the board, gravity and refills take it from
+/// there.
+fn cash_in(
+    mut commands: Commands,
+    mut events: EventReader<SmilerCashedIn>,
+    mut board: ResMut<Board>,
+    mut game_info: ResMut<GameInfo>,
+) {
+    for event in events.read() {
+        if let Some(entity) = board.entity(event.pos) {
+            commands.entity(entity).despawn_recursive();
+        }
+        board.set_entity(event.pos, None);
+        board.set(event.pos, None);
+        game_info.cashed_in += 1;
+    }
+}
+
+/// Runs the clock of a timed game, at the speed of the replay being played.
+/// It stops while the board settles, as no move can be made then.
+fn count_down(
+    time: Res<Time>,
+    player: Option<Res<ReplayPlayer>>,
+    settled: Res<Settled>,
+    mut game_info: ResMut<GameInfo>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(time_left) = &mut game_info.time_left else {
+        return;
+    };
+    if !settled.0 {
+        return;
+    }
+    let speed = match player {
+        Some(player) if player.is_paused() => 0.0,
+        Some(player) => player.speed(),
+        None => 1.0,
+    };
+    *time_left = (*time_left - time.delta_seconds() * speed).max(0.0);
+    if *time_left == 0.0 {
+        next_state.set(GameState::TimeUp);
+    }
+}
+
+fn end_game(
+    mut won: EventReader<GameWon>,
+    mut game_info: ResMut<GameInfo>,
+    mut profile: ResMut<PlayerProfile>,
+    mut next_state: ResMut<NextState<GameState>>,
+    setup: Res<GameSetup>,
+    campaign_handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+    player: Option<Res<ReplayPlayer>>,
+) {
+    for event in won.read() {
+        game_info.current_win_corrupted = event.corrupted;
+        next_state.set(GameState::Ending);
+        // Watching a replay doesn't earn anything.
+        if player.is_some() {
+            continue;
+        }
+        // Endings are about Pink Smilers, lower level goals don't count.
+        if event.pink {
+            profile.reach_ending(Ending::new(event.corrupted), event.moves);
+        }
+        let level = campaigns
+            .get(&campaign_handle.0)
+            .zip(setup.level_id.as_ref())
+            .and_then(|(campaign, id)| campaign.get(id));
+        if let Some(level) = level {
+            let stars = level.stars(event.corrupted, event.moves);
+            profile.record_stars(&level.id, stars);
+        }
+    }
+}
+
+/// Ends a level once its last allowed merge didn't reach the goal.
+fn detect_out_of_moves(
+    board: Res<Board>,
+    setup: Res<GameSetup>,
+    mut merged: EventReader<SmilerMerged>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(limit) = setup.move_limit else {
+        return;
+    };
+    for event in merged.read() {
+        if event.moves >= limit && !board.wins(&event.merge) {
+            next_state.set(GameState::OutOfMoves);
+        }
+    }
+}
+
+/// Ends the game once no two smilers can merge.
+fn detect_deadlock(board: Res<Board>, mut next_state: ResMut<NextState<GameState>>) {
+    if !board.has_merges() {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+/// Rearranges a stuck board so that the game goes on, once per game.
+pub fn shuffle_board(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut actions: EventReader<BoardAction>,
+    query: Query<Entity, With<Smiler>>,
+    texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut selection_sprite: ResMut<SelectionSprite>,
+    mut board: ResMut<Board>,
+    mut rng: ResMut<GameRng>,
+    mut game_info: ResMut<GameInfo>,
+    mut score_entry: ResMut<ScoreEntry>,
+    mut next_state: ResMut<NextState<GameState>>,
+    toast_area: Query<Entity, With<ToastArea>>,
+) {
+    let requested = actions
+        .read()
+        .any(|action| matches!(action, BoardAction::Shuffle));
+    if !requested || game_info.shuffled {
+        return;
+    }
+    let mut state = board.state.clone();
+    if !state.shuffle(&mut rng.board) {
+        let message = "No shuffle can save this board".to_string();
+        spawn_toast(&mut commands, toast_area.single(), &asset_server, message);
+        return;
+    }
+    game_info.shuffled = true;
+    // The game isn't over after all, its score is offered when it is.
+    if score_entry.0.take().is_some() {
+        game_info.score_offered = false;
+    }
+    next_state.set(GameState::Playing);
+
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(sprite) = selection_sprite.0.take() {
+        commands.entity(sprite).despawn();
+    }
+    board.reset(state);
+    spawn_smilers(commands, asset_server, texture_atlas_layouts, board, rng);
+}
+
+/// Traces every game event, run with `RUST_LOG=mergerration=debug` to see
+/// them.
+fn log_game_events(
+    mut merged: EventReader<SmilerMerged>,
+    mut corrupted: EventReader<SmilerCorrupted>,
+    mut spawned: EventReader<SmilerSpawned>,
+    mut landed: EventReader<SmilerLanded>,
+    mut settled: EventReader<BoardSettled>,
+    mut won: EventReader<GameWon>,
+) {
+    for event in merged.read() {
+        debug!("Merged {:?} (move {})", event.merge, event.moves);
+    }
+    for event in corrupted.read() {
+        debug!("Corrupted at {:?}", event.pos);
+    }
+    for event in spawned.read() {
+        debug!("Spawned {:?} at {:?}", event.cell, event.pos);
+    }
+    for event in landed.read() {
+        debug!("Landed {:?} at {:?}", event.entity, event.pos);
+    }
+    for _ in settled.read() {
+        debug!("Board settled");
+    }
+    for event in won.read() {
+        debug!(
+            "Won in {} moves, corrupted: {}",
+            event.moves, event.corrupted
+        );
+    }
+}
+
+/// Replaces the board with a fresh one from the game setup.
+fn start_new_game(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut new_games: EventReader<NewGame>,
+    query: Query<Entity, With<Smiler>>,
+    texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut selection_sprite: ResMut<SelectionSprite>,
+    mut board: ResMut<Board>,
+    mut rng: ResMut<GameRng>,
+    mut move_log: ResMut<MoveLog>,
+    mut profile: ResMut<PlayerProfile>,
+    mut game_info: ResMut<GameInfo>,
+    mut score_entry: ResMut<ScoreEntry>,
+    mut high_scores: ResMut<HighScoreTable>,
+    setup: Res<GameSetup>,
+    player: Option<Res<ReplayPlayer>>,
+) {
+    let Some(new_game) = new_games.read().last() else {
+        return;
+    };
+    // A high score still being named is kept under the name typed so far.
+    submit_score(&mut score_entry, &mut high_scores);
+    game_info.score_offered = false;
+    game_info.cashed_in = 0;
+    game_info.time_left = setup.mode.time_limit();
+    game_info.hints_used = 0;
+    game_info.shuffled = false;
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(sprite) = selection_sprite.0.take() {
+        commands.entity(sprite).despawn();
+    }
+    board.reset(setup.start.clone());
+    *rng = GameRng::new(new_game.seed);
+    move_log.clear();
+    if player.is_none() {
+        profile.games_played += 1;
+    }
+    spawn_smilers(commands, asset_server, texture_atlas_layouts, board, rng);
+    next_state.set(GameState::Playing);
+}
+
+/// Restores the board from the move log on undo and redo.
+fn undo_redo(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut queue: ResMut<ActionQueue>,
+    query: Query<Entity, With<Smiler>>,
+    texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut selection_sprite: ResMut<SelectionSprite>,
+    mut board: ResMut<Board>,
+    mut rng: ResMut<GameRng>,
+    mut move_log: ResMut<MoveLog>,
+    setup: Res<GameSetup>,
+) {
+    let mut snapshot = None;
+    while let Some(&action) = queue.0.front() {
+        let restored = match action {
+            // Undoing would give cashed in smilers back for another go.
+            BoardAction::Undo | BoardAction::Redo if setup.mode.cashes_in() => None,
+            BoardAction::Undo => move_log.undo().map(|step| step.before.clone()),
+            BoardAction::Redo => move_log.redo().map(|step| step.after.clone()),
+            // Left for `apply_board_actions`.
+            _ => break,
+        };
+        queue.0.pop_front();
+        snapshot = restored.or(snapshot);
+    }
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(sprite) = selection_sprite.0.take() {
+        commands.entity(sprite).despawn();
+    }
+    board.reset(snapshot.board);
+    board.deselect();
+    rng.board = snapshot.rng;
+    spawn_smilers(commands, asset_server, texture_atlas_layouts, board, rng);
+}
+
+/// A normal winning smiler was built in a mode that cashes them in.
+#[derive(Event)]
+struct SmilerCashedIn {
+    pos: Pos,
+}
+
+/// A merge was made; `moves` counts the merges of this game including it.
+#[derive(Event)]
+pub struct SmilerMerged {
+    pub merge: Merge,
+    pub moves: usize,
+}
+
+/// A mixed merge rolled a corrupted smiler.
+#[derive(Event)]
+pub struct SmilerCorrupted {
+    pub pos: Pos,
+}
+
+/// A smiler refilled an empty cell. It drops in once the board settles.
+#[derive(Event)]
+pub struct SmilerSpawned {
+    pub pos: Pos,
+    pub cell: Cell,
+}
+
+#[derive(Event)]
+pub struct GameWon {
+    pub corrupted: bool,
+    pub moves: usize,
+    /// The winning smiler is a Pink Smiler, not a lower level goal.
+    pub pink: bool,
+}
+
+/// What new games start from: a plain board of some shape, or a level.
+#[derive(Resource, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameSetup {
+    /// Name of the level being played.
+    pub level: Option<String>,
+    /// File name of that level, see `level_path`.
+    pub level_id: Option<String>,
+    pub start: rules::Board,
+    pub mode: Mode,
+    pub move_limit: Option<usize>,
+}
+
+impl GameSetup {
+    fn from_shape(shape: Shape, rules: RuleSet, mode: Mode) -> Self {
+        let mut start = rules::Board::new(shape);
+        start.set_rules(rules);
+        Self {
+            level: None,
+            level_id: None,
+            start,
+            mode,
+            move_limit: mode.move_limit(),
+        }
+    }
+
+    /// A game outside the campaign, by the rules file except for the daily
+    /// challenge, which is always played on the default board and rules.
+    pub fn free_play(shape: Shape, rules: &FreePlayRules, mode: Mode) -> Self {
+        match mode {
+            Mode::Daily => Self::from_shape(
+                Shape::rect(DEFAULT_BOARD_SIZE, DEFAULT_BOARD_SIZE),
+                RuleSet::default(),
+                mode,
+            ),
+            _ => Self::from_shape(shape, rules.0.clone(), mode),
+        }
+    }
+}
+
+/// Asks for a new game from the current `GameSetup`.
+#[derive(Event)]
+pub struct NewGame {
+    pub seed: u64,
+}
+
+/// What the player asked the board to do, whatever the input device.
+#[derive(Event, Clone, Copy, Debug)]
+pub enum BoardAction {
+    Select(Pos),
+    /// Merge two smilers directly, ignoring the current selection.
+    Merge {
+        from: Pos,
+        to: Pos,
+    },
+    Deselect,
+    Undo,
+    Redo,
+    /// Rearrange a stuck board.
+    Shuffle,
+}
+
+impl From<BoardAction> for replay::Action {
+    fn from(action: BoardAction) -> Self {
+        match action {
+            BoardAction::Select(pos) => Self::Select(pos),
+            BoardAction::Merge { from, to } => Self::Merge { from, to },
+            BoardAction::Deselect => Self::Deselect,
+            BoardAction::Undo => Self::Undo,
+            BoardAction::Redo => Self::Redo,
+            BoardAction::Shuffle => Self::Shuffle,
+        }
+    }
+}
+
+/// Actions waiting for their turn on the board.
+#[derive(Resource, Default)]
+struct ActionQueue(VecDeque<BoardAction>);
+
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct MoveLog {
+    #[deref]
+    pub history: History<ChaCha8Rng>,
+    /// Merge waiting for its refill before it gets recorded.
+    pending: Option<(Merge, Snapshot<ChaCha8Rng>)>,
+}
+
+/// Randomness of the current game. Everything derives from `seed`, so a game
+/// started with the same seed plays out the same way.
+#[derive(Resource)]
+pub struct GameRng {
+    pub seed: u64,
+    /// Refills and corruption rolls.
+    pub board: ChaCha8Rng,
+    /// Idle animations. They depend on frame timing, so they get their own
+    /// stream to never shift what happens on the board.
+    pub animation: ChaCha8Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let mut animation = ChaCha8Rng::seed_from_u64(seed);
+        animation.set_stream(1);
+        Self {
+            seed,
+            board: ChaCha8Rng::seed_from_u64(seed),
+            animation,
+        }
+    }
+}
+
+/// Fresh seed for a new game, kept short enough to be typed back in.
+pub fn new_seed() -> u64 {
+    random::<u32>().into()
+}
+
+/// Seed of a new game in `mode`: today's for the daily challenge.
+pub fn seed_for(mode: Mode) -> u64 {
+    match mode {
+        Mode::Daily => daily::seed(unix_time()),
+        _ => new_seed(),
+    }
+}
+
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct GameInfo {
+    pub current_win_corrupted: bool,
+    /// Winning smilers cashed in this game.
+    pub cashed_in: u32,
+    /// Seconds left in a timed game.
+    pub time_left: Option<f32>,
+    /// The game is over and was checked for a high score.
+    pub score_offered: bool,
+    pub hints_used: u32,
+    /// The shuffle power-up was used this game.
+    pub shuffled: bool,
+}
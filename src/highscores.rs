@@ -0,0 +1,154 @@
+//! Best finished runs, one table per mode and per level, kept across
+//! sessions.
+
+use std::{collections::BTreeMap, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{daily, mode::Mode, rules::Board, storage};
+
+const HIGH_SCORES_KEY: &str = "highscores";
+/// Entries kept in each table.
+pub const TABLE_SIZE: usize = 10;
+
+/// What a table ranks: free games of one mode, or one level.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Table {
+    Mode(Mode),
+    Level(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+    pub moves: usize,
+    pub seed: u64,
+    /// Unix time in seconds when the run ended.
+    pub time: u64,
+    /// Board the run started from, shape and rules, so mode entries replay
+    /// on it. Missing from entries stored before it was kept.
+    #[serde(default)]
+    pub start: Option<Board>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighScores {
+    /// Entries of each table, best first.
+    pub tables: BTreeMap<Table, Vec<Entry>>,
+    /// Name entered last, offered again for the next entry.
+    pub last_name: String,
+}
+
+impl HighScores {
+    /// Reads the stored tables, or starts empty ones if there are none. See
+    /// `storage::load_ron` for tables that can't be read.
+    pub fn load() -> Result<Self, String> {
+        storage::load_ron(HIGH_SCORES_KEY)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let contents = ron::to_string(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        storage::store(HIGH_SCORES_KEY, &contents)
+    }
+
+    pub fn table(&self, table: &Table) -> &[Entry] {
+        self.tables.get(table).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether `score` would make it into `table`.
+    pub fn qualifies(&self, table: &Table, score: u32) -> bool {
+        let entries = self.table(table);
+        score > 0
+            && (entries.len() < TABLE_SIZE || entries.last().is_some_and(|last| score > last.score))
+    }
+
+    /// Adds `entry` to `table` and returns its rank, from 0, if it stays in
+    /// the table. Ties rank below the entries already there.
+    pub fn insert(&mut self, table: Table, entry: Entry) -> Option<usize> {
+        self.last_name.clone_from(&entry.name);
+        let entries = self.tables.entry(table).or_default();
+        let rank = entries.partition_point(|other| other.score >= entry.score);
+        if rank >= TABLE_SIZE {
+            return None;
+        }
+        entries.insert(rank, entry);
+        entries.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+}
+
+/// `YYYY-MM-DD` date of a unix time, in UTC.
+pub fn date(time: u64) -> String {
+    let (year, month, day) = daily::civil_date(time);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32) -> Entry {
+        Entry {
+            name: name.to_string(),
+            score,
+            moves: 10,
+            seed: 1,
+            time: 0,
+            start: None,
+        }
+    }
+
+    fn scores(high_scores: &HighScores, table: &Table) -> Vec<u32> {
+        high_scores
+            .table(table)
+            .iter()
+            .map(|entry| entry.score)
+            .collect()
+    }
+
+    #[test]
+    fn insert_ranks_best_first() {
+        let table = Table::Mode(Mode::Classic);
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.insert(table.clone(), entry("a", 50)), Some(0));
+        assert_eq!(high_scores.insert(table.clone(), entry("b", 80)), Some(0));
+        // Ties go below.
+        assert_eq!(high_scores.insert(table.clone(), entry("c", 50)), Some(2));
+        assert_eq!(scores(&high_scores, &table), vec![80, 50, 50]);
+        assert_eq!(high_scores.table(&table)[1].name, "a");
+        assert_eq!(high_scores.last_name, "c");
+    }
+
+    #[test]
+    fn full_tables_keep_the_best() {
+        let table = Table::Level("pocket".to_string());
+        let mut high_scores = HighScores::default();
+        for score in 1..=TABLE_SIZE as u32 {
+            high_scores.insert(table.clone(), entry("a", score * 10));
+        }
+        assert!(!high_scores.qualifies(&table, 10));
+        assert!(high_scores.qualifies(&table, 11));
+        assert_eq!(high_scores.insert(table.clone(), entry("b", 5)), None);
+        assert_eq!(high_scores.insert(table.clone(), entry("b", 15)), Some(9));
+        assert_eq!(high_scores.table(&table).len(), TABLE_SIZE);
+        assert_eq!(high_scores.table(&table).last().unwrap().score, 15);
+    }
+
+    #[test]
+    fn qualifies_needs_points() {
+        let high_scores = HighScores::default();
+        let table = Table::Mode(Mode::Classic);
+        assert!(!high_scores.qualifies(&table, 0));
+        assert!(high_scores.qualifies(&table, 1));
+    }
+
+    #[test]
+    fn tables_are_separate() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(Table::Mode(Mode::Classic), entry("a", 50));
+        assert!(high_scores.table(&Table::Mode(Mode::Endless)).is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! Hints: a good merge for the board as it stands, picked by looking one
+//! move ahead rather than by a full search.
+
+use crate::rules::{Board, Cell, Pos};
+
+/// Hints a player can ask for in one game.
+pub const HINTS_PER_GAME: u32 = 3;
+/// Value of merging two corrupted smilers: no progress, but a cell freed
+/// for a refill that may well be normal.
+const CLEARING_VALUE: f64 = 0.1;
+/// Bonus for a normal result with a neighbor it can merge with next.
+const PARTNER_BONUS: f64 = 0.5;
+
+/// Suggests the merge most likely to lead toward a normal winning smiler,
+/// as `(from, to)`, or `None` if nothing can merge.
+pub fn suggest(board: &Board) -> Option<(Pos, Pos)> {
+    board
+        .merges()
+        .map(|(from, to)| ((from, to), value(board, from, to)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(merge, _)| merge)
+}
+
+/// How good merging `from` into `to` looks: the progress it makes toward a
+/// normal winning smiler if it comes out normal, minus the progress lost if
+/// a corruption roll goes wrong.
+fn value(board: &Board, from: Pos, to: Pos) -> f64 {
+    let (Some(source), Some(target)) = (board.get(from), board.get(to)) else {
+        return f64::NEG_INFINITY;
+    };
+    let progress = f64::from(target.phase) + 1.0;
+    let normal_chance = match (source.corrupted, target.corrupted) {
+        (false, false) => 1.0,
+        (true, true) => return CLEARING_VALUE,
+        _ => 1.0 - board.rules().mixed_merge_corruption(target.phase),
+    };
+    let result = Cell {
+        phase: target.phase + 1,
+        corrupted: false,
+    };
+    let has_partner = board
+        .neighbors(to)
+        .any(|pos| pos != from && board.get(pos) == Some(result));
+    let bonus = if has_partner { PARTNER_BONUS } else { 0.0 };
+    normal_chance * (progress + bonus) - (1.0 - normal_chance) * progress
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::Shape;
+
+    use super::*;
+
+    fn cell(phase: u8) -> Option<Cell> {
+        Some(Cell {
+            phase,
+            corrupted: false,
+        })
+    }
+
+    fn corrupted(phase: u8) -> Option<Cell> {
+        Some(Cell {
+            phase,
+            corrupted: true,
+        })
+    }
+
+    /// A single row of cells, from the left.
+    fn row(cells: &[Option<Cell>]) -> Board {
+        let mut board = Board::empty(Shape::rect(cells.len(), 1));
+        for (col, cell) in cells.iter().enumerate() {
+            board.set(Pos::new(col, 0), *cell);
+        }
+        board
+    }
+
+    #[test]
+    fn prefers_clean_merges() {
+        let board = row(&[cell(0), cell(0), corrupted(0)]);
+        let (from, to) = suggest(&board).unwrap();
+        let mut cols = [from.col, to.col];
+        cols.sort();
+        assert_eq!(cols, [0, 1]);
+    }
+
+    #[test]
+    fn prefers_results_with_a_partner() {
+        let board = row(&[cell(1), cell(0), cell(0)]);
+        assert_eq!(suggest(&board), Some((Pos::new(2, 0), Pos::new(1, 0))));
+    }
+
+    #[test]
+    fn clears_corrupted_pairs_rather_than_nothing() {
+        let board = row(&[corrupted(0), corrupted(0), cell(1)]);
+        let (from, to) = suggest(&board).unwrap();
+        let mut cols = [from.col, to.col];
+        cols.sort();
+        assert_eq!(cols, [0, 1]);
+    }
+
+    #[test]
+    fn nothing_on_a_stuck_board() {
+        let board = row(&[cell(0), cell(1), cell(0)]);
+        assert_eq!(suggest(&board), None);
+    }
+}
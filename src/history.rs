@@ -0,0 +1,171 @@
+//! Move log with undo and redo.
+//!
+//! Every move keeps the full board and random generator state from before
+//! and after it, so undoing restores the board exactly and redoing replays
+//! the very same outcome.
+
+use serde::{Deserialize, Serialize};
+
+use crate::rules::{Board, Cell, Merge, Pos};
+
+/// Board and random generator state at some point of a game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot<R> {
+    pub board: Board,
+    pub rng: R,
+}
+
+/// One merge with everything it caused.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Move<R> {
+    pub merge: Merge,
+    /// Smilers dropped in to fill the board afterwards.
+    pub refill: Vec<(Pos, Cell)>,
+    pub before: Snapshot<R>,
+    pub after: Snapshot<R>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct History<R> {
+    /// Every merge played and not undone, oldest first, including the ones
+    /// too old to undo.
+    merges: Vec<Merge>,
+    done: Vec<Move<R>>,
+    undone: Vec<Move<R>>,
+}
+
+impl<R> Default for History<R> {
+    fn default() -> Self {
+        Self {
+            merges: Vec::new(),
+            done: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
+}
+
+impl<R> History<R> {
+    /// Records a new move. Anything that was undone can't be redone anymore.
+    pub fn record(&mut self, step: Move<R>) {
+        self.undone.clear();
+        self.merges.push(step.merge);
+        self.done.push(step);
+    }
+
+    /// Forgets the states of all but the last `keep` moves, which can't be
+    /// undone anymore. Their merges still count.
+    pub fn forget(&mut self, keep: usize) {
+        let forgotten = self.done.len().saturating_sub(keep);
+        self.done.drain(..forgotten);
+    }
+
+    /// Takes back the last move and returns it; restore its `before` state.
+    pub fn undo(&mut self) -> Option<&Move<R>> {
+        let step = self.done.pop()?;
+        self.merges.pop();
+        self.undone.push(step);
+        self.undone.last()
+    }
+
+    /// Plays the last undone move again and returns it; restore its `after`
+    /// state.
+    pub fn redo(&mut self) -> Option<&Move<R>> {
+        let step = self.undone.pop()?;
+        self.merges.push(step.merge);
+        self.done.push(step);
+        self.done.last()
+    }
+
+    /// Merges played so far, oldest first.
+    pub fn merges(&self) -> &[Merge] {
+        &self.merges
+    }
+
+    /// Moves that can be undone, oldest first.
+    pub fn moves(&self) -> &[Move<R>] {
+        &self.done
+    }
+
+    /// Every state kept, to restore on undo or redo.
+    pub fn snapshots(&self) -> impl Iterator<Item = &Snapshot<R>> {
+        self.done
+            .iter()
+            .chain(&self.undone)
+            .flat_map(|step| [&step.before, &step.after])
+    }
+
+    pub fn clear(&mut self) {
+        self.merges.clear();
+        self.done.clear();
+        self.undone.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::Shape;
+
+    /// A move whose random generator state stands in for its number.
+    fn step(number: u32) -> Move<u32> {
+        let board = Board::new(Shape::rect(2, 1));
+        Move {
+            merge: Merge {
+                from: Pos::new(0, 0),
+                to: Pos::new(1, 0),
+                cell: Cell::default(),
+                mixed: false,
+            },
+            refill: Vec::new(),
+            before: Snapshot {
+                board: board.clone(),
+                rng: number,
+            },
+            after: Snapshot { board, rng: number },
+        }
+    }
+
+    fn numbers(moves: &[Move<u32>]) -> Vec<u32> {
+        moves.iter().map(|step| step.before.rng).collect()
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut history = History::default();
+        assert!(history.undo().is_none());
+        history.record(step(1));
+        history.record(step(2));
+        assert_eq!(history.undo().map(|step| step.before.rng), Some(2));
+        assert_eq!(numbers(history.moves()), vec![1]);
+        assert_eq!(history.redo().map(|step| step.after.rng), Some(2));
+        assert_eq!(numbers(history.moves()), vec![1, 2]);
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn record_drops_the_undone_moves() {
+        let mut history = History::default();
+        history.record(step(1));
+        history.record(step(2));
+        history.undo();
+        history.record(step(3));
+        assert!(history.redo().is_none());
+        assert_eq!(numbers(history.moves()), vec![1, 3]);
+    }
+
+    #[test]
+    fn forgotten_moves_still_count() {
+        let mut history = History::default();
+        history.record(step(1));
+        history.record(step(2));
+        history.record(step(3));
+        history.forget(1);
+        assert_eq!(numbers(history.moves()), vec![3]);
+        assert_eq!(history.merges().len(), 3);
+        history.undo();
+        assert!(history.undo().is_none());
+        assert_eq!(history.merges().len(), 2);
+        history.redo();
+        assert_eq!(history.merges().len(), 3);
+    }
+}
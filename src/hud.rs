@@ -0,0 +1,607 @@
+//! The game screen around the board: the text next to it, its buttons and
+//! hints.
+
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+use mergerration::{
+    campaign::MAX_STARS,
+    clipboard, daily,
+    hint::{self, HINTS_PER_GAME},
+    mode::Mode,
+    profile::Ending,
+    rules,
+    score::Score,
+};
+
+use crate::{
+    animation::board_settled,
+    assets::{level_path, Campaign, CampaignHandle, PendingLevel},
+    board::{cell_translation, Board, BOARD_CENTER},
+    game::{
+        new_seed, seed_for, BoardAction, GameInfo, GameRng, GameSet, GameSetup, MoveLog, NewGame,
+    },
+    in_game,
+    input::gamepad_just_pressed,
+    layout::{UiRoot, HINT_OFFSET, WINDOW_HEIGHT, WINDOW_WIDTH},
+    menu::ResumeState,
+    overlay::SolverText,
+    progress::{Achievements, AchievementsHandle, PlayerProfile},
+    recording::ReplayPlayer,
+    scoreboard::ScoreEntry,
+    ui::{button_style, clicked, show, spawn_button, spawn_toast, ToastArea},
+    GameState,
+};
+
+const HINT_SECONDS: f32 = 4.0;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_stuff).add_systems(
+            Update,
+            (
+                request_hint
+                    .run_if(not(resource_exists::<ReplayPlayer>).and_then(board_settled))
+                    .in_set(GameSet::Input),
+                update_hint_highlights,
+                (
+                    restart.run_if(not(resource_exists::<ReplayPlayer>)),
+                    game_buttons,
+                )
+                    .run_if(in_game)
+                    .in_set(GameSet::Request),
+                update_button_bar,
+                share_result.run_if(in_game),
+                update_seed_text,
+                update_text,
+            ),
+        );
+    }
+}
+
+fn spawn_stuff(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let hint = BOARD_CENTER + HINT_OFFSET;
+    commands.spawn((
+        SpriteBundle {
+            texture: asset_server.load("hint.png"),
+            transform: Transform::from_xyz(hint.x, hint.y, 1.0).with_scale(Vec3::splat(1.5)),
+            ..default()
+        },
+        Hint,
+    ));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(WINDOW_WIDTH),
+                            height: Val::Px(WINDOW_HEIGHT),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    UiRoot,
+                ))
+                .with_children(|parent| spawn_ui(parent, &asset_server));
+        });
+}
+
+fn spawn_ui(parent: &mut ChildBuilder, asset_server: &AssetServer) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.0),
+                    column_gap: Val::Px(20.0),
+                    left: Val::Px(870.0),
+                    bottom: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+            ButtonBar,
+        ))
+        .with_children(|parent| {
+            spawn_button(parent, asset_server, "NEXT LEVEL", NextLevelButton).insert(Style {
+                display: Display::None,
+                ..button_style(50.0)
+            });
+            spawn_button(parent, asset_server, "SAVE SCORE", SaveScoreButton).insert(Style {
+                display: Display::None,
+                ..button_style(50.0)
+            });
+            spawn_button(parent, asset_server, "SHARE", ShareButton).insert(Style {
+                display: Display::None,
+                ..button_style(50.0)
+            });
+            spawn_button(parent, asset_server, "SHUFFLE", ShuffleButton).insert(Style {
+                display: Display::None,
+                ..button_style(50.0)
+            });
+            spawn_button(parent, asset_server, "HINT", HintButton).insert(Style {
+                display: Display::None,
+                ..button_style(50.0)
+            });
+            spawn_button(parent, asset_server, "TRY AGAIN", RestartButton);
+            spawn_button(parent, asset_server, "MENU", MenuButton).insert(button_style(50.0));
+        });
+    parent.spawn((
+        TextBundle::from_section(
+            "Can you build a Pink Smiler?",
+            TextStyle {
+                font: asset_server.load("Marinda.ttf"),
+                font_size: 30.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(810.0),
+            right: Val::Px(30.0),
+            top: Val::Px(120.0),
+            bottom: Val::Px(200.0),
+            ..default()
+        }),
+        GameText,
+    ));
+    parent.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("Marinda.ttf"),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            bottom: Val::Px(10.0),
+            ..default()
+        }),
+        SeedText,
+    ));
+    parent.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("Marinda.ttf"),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            top: Val::Px(10.0),
+            ..default()
+        }),
+        SolverText,
+    ));
+    parent.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+        ToastArea,
+    ));
+}
+
+/// Starts a new game from the button, R or the gamepad Start button.
+fn restart(
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    button_query: Query<&RelativeCursorPosition, With<RestartButton>>,
+    score_entry: Res<ScoreEntry>,
+    setup: Res<GameSetup>,
+    mut new_games: EventWriter<NewGame>,
+) {
+    let button = button_query.single();
+
+    // R is typed into the name of a high score instead.
+    let typing = score_entry.0.is_some();
+    if clicked(button, &mouse_button_input, &touches)
+        || (keyboard_input.just_pressed(KeyCode::KeyR) && !typing)
+        || gamepad_just_pressed(&gamepad_buttons, GamepadButtonType::Start)
+    {
+        new_games.send(NewGame {
+            seed: seed_for(setup.mode),
+        });
+    }
+}
+
+/// The in-game MENU and NEXT LEVEL buttons.
+fn game_buttons(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    menu_button: Query<&RelativeCursorPosition, With<MenuButton>>,
+    next_button: Query<&RelativeCursorPosition, With<NextLevelButton>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut resume: ResMut<ResumeState>,
+    setup: Res<GameSetup>,
+    campaign_handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+) {
+    if clicked(menu_button.single(), &mouse_button_input, &touches) {
+        resume.0 = *state.get();
+        next_state.set(GameState::MainMenu);
+    } else if clicked(next_button.single(), &mouse_button_input, &touches) {
+        let next = campaigns
+            .get(&campaign_handle.0)
+            .zip(setup.level_id.as_ref())
+            .and_then(|(campaign, id)| campaign.next(id));
+        if let Some(next) = next {
+            commands.remove_resource::<ReplayPlayer>();
+            commands.insert_resource(PendingLevel {
+                handle: asset_server.load(level_path(&next.id)),
+                id: next.id.clone(),
+                seed: new_seed(),
+            });
+        }
+    }
+}
+
+/// Offers the next campaign level once the current one is won, saving a
+/// new high score, sharing a finished daily challenge, hints while playing
+/// and the shuffle once the board is stuck.
+fn update_button_bar(
+    state: Res<State<GameState>>,
+    setup: Res<GameSetup>,
+    campaign_handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+    score_entry: Res<ScoreEntry>,
+    game_info: Res<GameInfo>,
+    player: Option<Res<ReplayPlayer>>,
+    mut buttons: Query<
+        (
+            &mut Style,
+            Has<NextLevelButton>,
+            Has<SaveScoreButton>,
+            Has<ShareButton>,
+            Has<ShuffleButton>,
+        ),
+        Or<(
+            With<NextLevelButton>,
+            With<SaveScoreButton>,
+            With<ShareButton>,
+            With<ShuffleButton>,
+            With<HintButton>,
+        )>,
+    >,
+) {
+    let has_next = campaigns
+        .get(&campaign_handle.0)
+        .zip(setup.level_id.as_ref())
+        .is_some_and(|(campaign, id)| campaign.next(id).is_some());
+    for (mut style, next_level, save_score, share, shuffle) in &mut buttons {
+        let visible = if next_level {
+            has_next && *state.get() == GameState::Ending
+        } else if save_score {
+            score_entry.0.is_some()
+        } else if share {
+            setup.mode == Mode::Daily && state.get().is_over()
+        } else if shuffle {
+            *state.get() == GameState::GameOver && !game_info.shuffled && player.is_none()
+        } else {
+            *state.get() == GameState::Playing && player.is_none()
+        };
+        show(&mut style, visible);
+    }
+}
+
+/// Highlights a suggested merge on H, the north face button or the HINT
+/// button, as long as the game has hints left.
+fn request_hint(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    hint_button: Query<&RelativeCursorPosition, With<HintButton>>,
+    board: Res<Board>,
+    mut game_info: ResMut<GameInfo>,
+    mut profile: ResMut<PlayerProfile>,
+    highlights: Query<(), With<HintHighlight>>,
+    toast_area: Query<Entity, With<ToastArea>>,
+) {
+    let requested = keyboard_input.just_pressed(KeyCode::KeyH)
+        || gamepad_just_pressed(&gamepad_buttons, GamepadButtonType::North)
+        || clicked(hint_button.single(), &mouse_button_input, &touches);
+    // Asking again while a hint is on screen doesn't cost another one.
+    if !requested || !highlights.is_empty() {
+        return;
+    }
+    if game_info.hints_used >= HINTS_PER_GAME {
+        let message = "No hints left this game".to_string();
+        spawn_toast(&mut commands, toast_area.single(), &asset_server, message);
+        return;
+    }
+    let Some((from, to)) = hint::suggest(&board) else {
+        let message = "Nothing can merge".to_string();
+        spawn_toast(&mut commands, toast_area.single(), &asset_server, message);
+        return;
+    };
+    game_info.hints_used += 1;
+    profile.hints_used += 1;
+    for pos in [from, to] {
+        let coords = cell_translation(&board, pos);
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("selection.png"),
+                sprite: Sprite {
+                    color: Color::rgba(0.6, 1.0, 0.6, 0.9),
+                    ..default()
+                },
+                transform: Transform::from_xyz(coords.x, coords.y, 1.5)
+                    .with_scale(Vec3::splat(0.7)),
+                ..default()
+            },
+            HintHighlight(Timer::from_seconds(HINT_SECONDS, TimerMode::Once)),
+        ));
+    }
+}
+
+/// Hint highlights last a few seconds, or until the player does anything.
+fn update_hint_highlights(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut actions: EventReader<BoardAction>,
+    mut new_games: EventReader<NewGame>,
+    mut query: Query<(Entity, &mut HintHighlight)>,
+) {
+    let acted = actions.read().count() + new_games.read().count() > 0;
+    for (entity, mut highlight) in &mut query {
+        if highlight.0.tick(time.delta()).finished() || acted {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Copies the result of a finished daily challenge to the clipboard.
+fn share_result(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    share_button: Query<(&RelativeCursorPosition, &Style), With<ShareButton>>,
+    state: Res<State<GameState>>,
+    rng: Res<GameRng>,
+    move_log: Res<MoveLog>,
+    game_info: Res<GameInfo>,
+    toast_area: Query<Entity, With<ToastArea>>,
+) {
+    let (button, style) = share_button.single();
+    if style.display == Display::None || !clicked(button, &mouse_button_input, &touches) {
+        return;
+    }
+    let ending =
+        (*state.get() == GameState::Ending).then(|| Ending::new(game_info.current_win_corrupted));
+    let score = Score::from_merges(move_log.merges());
+    let text = daily::share_text(rng.seed, ending, move_log.merges().len(), score.points);
+    let message = match clipboard::copy(&text) {
+        Ok(()) => "Result copied to the clipboard".to_string(),
+        Err(error) => {
+            warn!("Failed to copy the result: {error}");
+            info!("{text}");
+            "Couldn't copy the result".to_string()
+        }
+    };
+    spawn_toast(&mut commands, toast_area.single(), &asset_server, message);
+}
+
+fn update_text(
+    game_info: Res<GameInfo>,
+    score_entry: Res<ScoreEntry>,
+    profile: Res<PlayerProfile>,
+    board: Res<Board>,
+    setup: Res<GameSetup>,
+    move_log: Res<MoveLog>,
+    achievements_handle: Res<AchievementsHandle>,
+    achievements_assets: Res<Assets<Achievements>>,
+    mut query: Query<&mut Text, With<GameText>>,
+    state: Res<State<GameState>>,
+    player: Option<Res<ReplayPlayer>>,
+) {
+    let mut text = query.single_mut();
+    let endings = profile.endings.len();
+    let achievements = achievements_assets
+        .get(&achievements_handle.0)
+        .map_or(String::new(), |registry| {
+            let unlocked = |hidden| {
+                registry
+                    .achievements
+                    .iter()
+                    .filter(|achievement| {
+                        achievement.hidden == hidden && profile.has_achievement(&achievement.id)
+                    })
+                    .count()
+            };
+            format!(
+                "Achievements: {}/{}\n\nSecret achievements: {}/{}",
+                unlocked(false),
+                registry.count(false),
+                unlocked(true),
+                registry.count(true),
+            )
+        });
+	let text_str;
+    let win_phase = board.rules().win_phase;
+    if *state.get() == GameState::Ending && win_phase != rules::WIN_PHASE {
+        let smiler = if game_info.current_win_corrupted {
+            "a corrupted"
+        } else {
+            "a"
+        };
+        text_str = format!(
+            "Level complete!\n\nYou've built {smiler} phase {win_phase} smiler in {} moves.\n\n{}",
+            move_log.merges().len(),
+            achievements
+        );
+    } else if *state.get() == GameState::Ending {
+        if game_info.current_win_corrupted {
+            text_str = format!(
+                "Congratulations!\n
+				You've built... corrupted Pink Smiler.
+			Was it your goal?\nYou know it will destroy the world now, right?\n
+			Endings: {}/2\n\n{}",
+                endings,
+                achievements,
+            );
+        } else {
+            text_str = format!(
+                "Congratulations!\n
+			You've built a Pink Smiler!
+			Now it will bring peace and solve all the world problems.
+			What a nice victory!\n\nEndings: {}/2\n\n{}",
+                endings,
+                achievements
+            );
+        }
+    } else if *state.get() == GameState::OutOfMoves {
+        text_str = "Out of moves!\n\nThe smilers are waiting for you to try again.".to_string();
+    } else if *state.get() == GameState::TimeUp {
+        text_str = "Time's up!".to_string();
+    } else if *state.get() == GameState::GameOver {
+        text_str = if game_info.shuffled || player.is_some() {
+            "No merges left!\n\nNo two neighbors are alike anymore.".to_string()
+        } else {
+            "No merges left!\n\nNo two neighbors are alike anymore.\n\nShuffle the board (Tab) to keep going?".to_string()
+        };
+    } else {
+        let goal = if win_phase == rules::WIN_PHASE {
+            "a Pink Smiler".to_string()
+        } else {
+            format!("a phase {win_phase} smiler")
+        };
+        let mut lines = vec![if setup.mode.cashes_in() {
+            format!("Cash in {goal} as often as you can, but keep it normal!")
+        } else {
+            format!("Can you build {goal}?")
+        }];
+        if let Some(name) = &setup.level {
+            lines.insert(0, name.clone());
+        } else if setup.mode != Mode::Classic {
+            lines.insert(0, setup.mode.name().to_string());
+        }
+        if let Some(time_left) = game_info.time_left {
+            let seconds = time_left.ceil() as u32;
+            lines.push(format!("Time left: {}:{:02}", seconds / 60, seconds % 60));
+        }
+        if let Some(limit) = setup.move_limit {
+            let left = limit.saturating_sub(move_log.merges().len());
+            lines.push(format!("Moves left: {left}"));
+        }
+        let hints_left = HINTS_PER_GAME.saturating_sub(game_info.hints_used);
+        lines.push(format!("Hints left: {hints_left}"));
+        text_str = lines.join("\n\n");
+    }
+    let score = Score::from_merges(move_log.merges());
+    let mut extra = if *state.get() == GameState::Playing {
+        format!(
+            "\n\nScore: {}   x{:.2}",
+            score.points,
+            score.multiplier_percent() as f32 / 100.0
+        )
+    } else {
+        format!("\n\nScore: {}", score.points)
+    };
+    if setup.mode.cashes_in() {
+        extra += &format!("\n\nCashed in: {}", game_info.cashed_in);
+    }
+    if let Some((_, entry)) = &score_entry.0 {
+        extra += &format!("\n\nNew high score! Type your name:\n{}_", entry.name);
+    }
+    if let Some(id) = &setup.level_id {
+        if *state.get() == GameState::Ending {
+            extra += &format!("\n\nBest: {}/{MAX_STARS} stars", profile.stars(id));
+        }
+    }
+    if let Some(player) = player {
+        let status = if player.is_paused() {
+            "paused"
+        } else {
+            "playing"
+        };
+        extra += &format!(
+            "\n\nReplay {status} at x{}\nSpace: pause  Right: step\nUp/Down: speed  Esc: stop",
+            player.speed()
+        );
+    }
+    text.sections[0].value = text_str + &extra;
+}
+
+fn update_seed_text(rng: Res<GameRng>, mut query: Query<&mut Text, With<SeedText>>) {
+    let value = format!("Seed: {}", rng.seed);
+    let mut text = query.single_mut();
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+#[derive(Component)]
+pub struct GameText;
+
+#[derive(Component)]
+struct SeedText;
+
+#[derive(Component)]
+struct RestartButton;
+
+#[derive(Component)]
+pub struct ButtonBar;
+
+#[derive(Component)]
+struct NextLevelButton;
+
+#[derive(Component)]
+struct MenuButton;
+
+#[derive(Component)]
+pub struct SaveScoreButton;
+
+#[derive(Component)]
+struct ShareButton;
+
+#[derive(Component)]
+struct HintButton;
+
+#[derive(Component)]
+pub struct ShuffleButton;
+
+/// Marks a cell of the suggested merge until its timer runs out.
+#[derive(Component)]
+struct HintHighlight(Timer);
+
+#[derive(Component)]
+pub struct Hint;
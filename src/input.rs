@@ -0,0 +1,321 @@
+//! Mouse, touch, keyboard and gamepad input on the board.
+
+use bevy::{prelude::*, ui::RelativeCursorPosition, window::PrimaryWindow};
+use mergerration::rules::Pos;
+
+use crate::{
+    animation::board_settled,
+    board::{cell_at, cell_translation, Board},
+    game::{shuffle_board, BoardAction, GameSet},
+    hud::ShuffleButton,
+    layout::MainCamera,
+    recording::ReplayPlayer,
+    ui::clicked,
+    GameState,
+};
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BoardCursor {
+            pos: Pos::new(0, 0),
+            active: false,
+        })
+        .insert_resource(CursorCoords(None))
+        .add_systems(Startup, spawn_board_cursor)
+        .add_systems(
+            Update,
+            (
+                (
+                    update_cursor_coords,
+                    (
+                        mouse_input_playing,
+                        touch_input_playing,
+                        board_navigation,
+                        undo_redo_input,
+                    )
+                        .run_if(not(resource_exists::<ReplayPlayer>).and_then(board_settled)),
+                )
+                    .chain()
+                    .in_set(GameSet::Input),
+                update_board_cursor,
+                shuffle_input
+                    .before(shuffle_board)
+                    .run_if(in_state(GameState::GameOver))
+                    .run_if(not(resource_exists::<ReplayPlayer>)),
+            ),
+        );
+    }
+}
+
+fn update_cursor_coords(
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut cursor_coords: ResMut<CursorCoords>,
+) {
+    let (camera, camera_transform) = q_camera.single();
+    let window = q_window.single();
+
+    cursor_coords.0 = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+        .map(|ray| ray.origin.truncate());
+}
+
+fn mouse_input_playing(
+    cursor_coords: Res<CursorCoords>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    board: Res<Board>,
+    mut cursor: ResMut<BoardCursor>,
+    mut actions: EventWriter<BoardAction>,
+) {
+    if !mouse_button_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if let Some(pos) = cursor_coords.0.and_then(|coords| cell_at(&board, coords)) {
+        cursor.active = false;
+        actions.send(BoardAction::Select(pos));
+    }
+}
+
+/// Tapping a smiler works like clicking it, dragging one onto a neighbor
+/// merges them.
+fn touch_input_playing(
+    touches: Res<Touches>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    board: Res<Board>,
+    mut cursor: ResMut<BoardCursor>,
+    mut actions: EventWriter<BoardAction>,
+) {
+    let (camera, camera_transform) = q_camera.single();
+    let touched_cell = |position| {
+        camera
+            .viewport_to_world(camera_transform, position)
+            .and_then(|ray| cell_at(&board, ray.origin.truncate()))
+    };
+
+    for touch in touches.iter_just_released() {
+        let (Some(from), Some(to)) = (
+            touched_cell(touch.start_position()),
+            touched_cell(touch.position()),
+        ) else {
+            continue;
+        };
+        cursor.active = false;
+        actions.send(if from == to {
+            BoardAction::Select(to)
+        } else {
+            BoardAction::Merge { from, to }
+        });
+    }
+}
+
+/// Moves the board cursor with arrows, WASD, the D-pad or the left stick,
+/// and selects under it with Enter, Space or the south face button. Escape,
+/// Backspace or the east face button drop the selection.
+fn board_navigation(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    board: Res<Board>,
+    mut cursor: ResMut<BoardCursor>,
+    mut actions: EventWriter<BoardAction>,
+    mut last_stick: Local<IVec2>,
+) {
+    let pressed = |keys: [KeyCode; 2], button_type: GamepadButtonType| {
+        keyboard_input.any_just_pressed(keys) || gamepad_just_pressed(&gamepad_buttons, button_type)
+    };
+    let mut step = IVec2::ZERO;
+    if pressed(
+        [KeyCode::ArrowLeft, KeyCode::KeyA],
+        GamepadButtonType::DPadLeft,
+    ) {
+        step.x -= 1;
+    }
+    if pressed(
+        [KeyCode::ArrowRight, KeyCode::KeyD],
+        GamepadButtonType::DPadRight,
+    ) {
+        step.x += 1;
+    }
+    if pressed(
+        [KeyCode::ArrowDown, KeyCode::KeyS],
+        GamepadButtonType::DPadDown,
+    ) {
+        step.y -= 1;
+    }
+    if pressed([KeyCode::ArrowUp, KeyCode::KeyW], GamepadButtonType::DPadUp) {
+        step.y += 1;
+    }
+
+    // The stick moves one cell each time it is pushed out of its dead zone.
+    let stick = gamepads
+        .iter()
+        .map(|gamepad| {
+            let axis = |axis_type| {
+                gamepad_axes
+                    .get(GamepadAxis::new(gamepad, axis_type))
+                    .unwrap_or_default()
+            };
+            let direction = |value: f32| {
+                if value.abs() < 0.5 {
+                    0
+                } else {
+                    value.signum() as i32
+                }
+            };
+            IVec2::new(
+                direction(axis(GamepadAxisType::LeftStickX)),
+                direction(axis(GamepadAxisType::LeftStickY)),
+            )
+        })
+        .find(|direction| *direction != IVec2::ZERO)
+        .unwrap_or_default();
+    if stick != *last_stick {
+        step += stick;
+    }
+    *last_stick = stick;
+
+    if step != IVec2::ZERO {
+        if cursor.active {
+            // Holes in the board are skipped, the edge stops the cursor.
+            let step = step.signum();
+            let mut at = IVec2::new(cursor.pos.col as i32, cursor.pos.row as i32) + step;
+            while at.cmpge(IVec2::ZERO).all()
+                && at.x < board.width() as i32
+                && at.y < board.height() as i32
+            {
+                let pos = Pos::new(at.x as usize, at.y as usize);
+                if board.contains(pos) {
+                    cursor.pos = pos;
+                    break;
+                }
+                at += step;
+            }
+        }
+        cursor.active = true;
+    }
+
+    if pressed([KeyCode::Enter, KeyCode::Space], GamepadButtonType::South) {
+        if cursor.active {
+            actions.send(BoardAction::Select(cursor.pos));
+        }
+        cursor.active = true;
+    }
+    if pressed(
+        [KeyCode::Escape, KeyCode::Backspace],
+        GamepadButtonType::East,
+    ) {
+        actions.send(BoardAction::Deselect);
+    }
+}
+
+pub fn gamepad_just_pressed(
+    gamepad_buttons: &ButtonInput<GamepadButton>,
+    button_type: GamepadButtonType,
+) -> bool {
+    gamepad_buttons
+        .get_just_pressed()
+        .any(|button| button.button_type == button_type)
+}
+
+fn spawn_board_cursor(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        SpriteBundle {
+            texture: asset_server.load("selection.png"),
+            sprite: Sprite {
+                color: Color::rgba(1.0, 0.9, 0.5, 0.9),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 2.0).with_scale(Vec3::splat(0.7)),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        BoardCursorSprite,
+    ));
+}
+
+/// Keeps the board cursor on the board, moving it to the nearest cell when
+/// the board changes shape under it.
+fn update_board_cursor(
+    board: Res<Board>,
+    mut cursor: ResMut<BoardCursor>,
+    state: Res<State<GameState>>,
+    mut query: Query<(&mut Transform, &mut Visibility), With<BoardCursorSprite>>,
+) {
+    if !board.contains(cursor.pos) {
+        let distance =
+            |pos: Pos| pos.col.abs_diff(cursor.pos.col) + pos.row.abs_diff(cursor.pos.row);
+        if let Some(pos) = board.positions().min_by_key(|pos| distance(*pos)) {
+            cursor.pos = pos;
+        }
+    }
+    let (mut transform, mut visibility) = query.single_mut();
+    let coords = cell_translation(&board, cursor.pos);
+    transform.translation.x = coords.x;
+    transform.translation.y = coords.y;
+    *visibility = if cursor.active && *state.get() == GameState::Playing {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+}
+
+/// Offers the shuffle power-up on the SHUFFLE button, Tab or the south face
+/// button once the board is stuck.
+fn shuffle_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    shuffle_button: Query<(&RelativeCursorPosition, &Style), With<ShuffleButton>>,
+    mut actions: EventWriter<BoardAction>,
+) {
+    let (button, style) = shuffle_button.single();
+    if style.display == Display::None {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Tab)
+        || gamepad_just_pressed(&gamepad_buttons, GamepadButtonType::South)
+        || clicked(button, &mouse_button_input, &touches)
+    {
+        actions.send(BoardAction::Shuffle);
+    }
+}
+
+/// Ctrl+Z or the left trigger take back the last merge, Ctrl+Y, Ctrl+Shift+Z
+/// or the right trigger play it again.
+fn undo_redo_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut actions: EventWriter<BoardAction>,
+) {
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if (ctrl && !shift && keyboard_input.just_pressed(KeyCode::KeyZ))
+        || gamepad_just_pressed(&gamepad_buttons, GamepadButtonType::LeftTrigger)
+    {
+        actions.send(BoardAction::Undo);
+    } else if (ctrl && keyboard_input.just_pressed(KeyCode::KeyY))
+        || (ctrl && shift && keyboard_input.just_pressed(KeyCode::KeyZ))
+        || gamepad_just_pressed(&gamepad_buttons, GamepadButtonType::RightTrigger)
+    {
+        actions.send(BoardAction::Redo);
+    }
+}
+
+/// Cell highlighted for keyboard and gamepad play. It only shows once one of
+/// those is used, and hides again on mouse clicks.
+#[derive(Resource)]
+struct BoardCursor {
+    pos: Pos,
+    active: bool,
+}
+
+#[derive(Component)]
+struct BoardCursorSprite;
+
+#[derive(Resource)]
+struct CursorCoords(Option<Vec2>);
@@ -0,0 +1,125 @@
+//! Fitting the game to the window, in landscape or portrait.
+
+use bevy::{prelude::*, render::camera::ScalingMode, window::PrimaryWindow};
+
+use crate::{
+    board::{Board, BOARD_CENTER, CELL_INTERVAL, CELL_SIZE},
+    hud::{ButtonBar, GameText, Hint},
+};
+
+pub const WINDOW_WIDTH: f32 = 1200.0;
+pub const WINDOW_HEIGHT: f32 = 720.0;
+/// Width of the UI when the screen is taller than wide.
+const PORTRAIT_WIDTH: f32 = 720.0;
+/// World area kept in view in portrait: the board and the hint next to it.
+const PORTRAIT_VIEW: Rect = Rect {
+    min: Vec2::new(-580.0, -320.0),
+    max: Vec2::new(190.0, 320.0),
+};
+/// Side of the world area a board fits in before the view zooms out.
+const BOARD_AREA: f32 = 600.0;
+/// The rules hint, relative to the board center.
+pub const HINT_OFFSET: Vec2 = Vec2::new(-380.0, 0.0);
+
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_camera)
+            .add_systems(Update, update_layout);
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), MainCamera));
+}
+
+/// Fits the game to the window: the landscape layout is scaled as a whole,
+/// while on portrait screens the board fills the width with the text and
+/// the button below it. Boards too big for their area zoom the view out.
+fn update_layout(
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_camera: Query<(&mut OrthographicProjection, &mut Transform), With<MainCamera>>,
+    mut q_hint: Query<&mut Transform, (With<Hint>, Without<MainCamera>)>,
+    board: Res<Board>,
+    mut ui_scale: ResMut<UiScale>,
+    mut ui_root: Query<&mut Style, (With<UiRoot>, Without<GameText>, Without<ButtonBar>)>,
+    mut game_text: Query<&mut Style, (With<GameText>, Without<ButtonBar>)>,
+    mut button_bar: Query<&mut Style, (With<ButtonBar>, Without<GameText>)>,
+    mut last_layout: Local<(Vec2, f32)>,
+) {
+    let window = q_window.single();
+    let size = Vec2::new(window.width(), window.height());
+    let extent = board.width().max(board.height()) as f32 * (CELL_SIZE + CELL_INTERVAL);
+    let zoom = (extent / BOARD_AREA).max(1.0);
+    if (size, zoom) == *last_layout || size.min_element() <= 0.0 {
+        return;
+    }
+    *last_layout = (size, zoom);
+
+    // Zooming happens around the board center, so the board keeps its place
+    // next to the text.
+    let mut hint = q_hint.single_mut();
+    let hint_coords = BOARD_CENTER + HINT_OFFSET * zoom;
+    hint.translation = hint_coords.extend(hint.translation.z);
+    hint.scale = Vec3::splat(1.5 * zoom);
+
+    let (mut projection, mut camera_transform) = q_camera.single_mut();
+    let mut root = ui_root.single_mut();
+    let mut text = game_text.single_mut();
+    let mut buttons = button_bar.single_mut();
+
+    if size.x >= size.y {
+        projection.scaling_mode = ScalingMode::AutoMin {
+            min_width: WINDOW_WIDTH * zoom,
+            min_height: WINDOW_HEIGHT * zoom,
+        };
+        let center = BOARD_CENTER * (1.0 - zoom);
+        camera_transform.translation = center.extend(camera_transform.translation.z);
+        ui_scale.0 = (size.x / WINDOW_WIDTH).min(size.y / WINDOW_HEIGHT);
+        root.width = Val::Px(WINDOW_WIDTH);
+        root.height = Val::Px(WINDOW_HEIGHT);
+        text.left = Val::Px(810.0);
+        text.right = Val::Px(30.0);
+        text.top = Val::Px(120.0);
+        text.bottom = Val::Px(200.0);
+        buttons.flex_direction = FlexDirection::Column;
+        buttons.left = Val::Px(870.0);
+        buttons.right = Val::Auto;
+        buttons.bottom = Val::Px(20.0);
+    } else {
+        let view = Rect::from_corners(
+            BOARD_CENTER + (PORTRAIT_VIEW.min - BOARD_CENTER) * zoom,
+            BOARD_CENTER + (PORTRAIT_VIEW.max - BOARD_CENTER) * zoom,
+        );
+        let view_width = view.width();
+        let view_height = view_width * size.y / size.x;
+        projection.scaling_mode = ScalingMode::FixedHorizontal(view_width);
+        camera_transform.translation = Vec3::new(
+            view.center().x,
+            view.max.y - view_height / 2.0,
+            camera_transform.translation.z,
+        );
+        ui_scale.0 = size.x / PORTRAIT_WIDTH;
+        let height = size.y / ui_scale.0;
+        let board_bottom = view.height() * PORTRAIT_WIDTH / view_width;
+        root.width = Val::Px(PORTRAIT_WIDTH);
+        root.height = Val::Px(height);
+        text.left = Val::Px(20.0);
+        text.right = Val::Px(20.0);
+        text.top = Val::Px(board_bottom + 20.0);
+        text.bottom = Val::Px(120.0);
+        buttons.flex_direction = FlexDirection::Row;
+        buttons.justify_content = JustifyContent::Center;
+        buttons.left = Val::Px(0.0);
+        buttons.right = Val::Px(0.0);
+        buttons.bottom = Val::Px(27.5);
+    }
+}
+
+/// Box holding the whole UI, sized and scaled to fit the window.
+#[derive(Component)]
+pub struct UiRoot;
+
+#[derive(Component)]
+pub struct MainCamera;
@@ -0,0 +1,131 @@
+//! Hand-authored puzzles: a starting board, where new smilers come from and
+//! what the player has to build.
+
+use serde::Deserialize;
+
+use crate::rules::{Board, Cell, Pos, Refill, RuleSet, Shape};
+
+/// A level as written in a `.level.ron` file.
+///
+/// `board` lists rows from top to bottom with cells separated by spaces:
+/// `.` is a hole, `_` an empty cell, a number a normal smiler of that phase
+/// and a number followed by `c` a corrupted one, e.g. `"0 1c _ ."`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Level {
+    pub name: String,
+    pub board: Vec<String>,
+    #[serde(default)]
+    pub refill: Refill,
+    /// Rules the level is played by, the defaults for anything left out.
+    #[serde(default)]
+    pub rules: RuleSet,
+    /// Merges allowed before the level is lost.
+    #[serde(default)]
+    pub move_limit: Option<usize>,
+}
+
+impl Level {
+    /// Builds the starting board described by the level.
+    pub fn board(&self) -> Result<Board, String> {
+        self.rules.validate()?;
+        let rows = self
+            .board
+            .iter()
+            .map(|row| {
+                row.split_whitespace()
+                    .map(parse_cell)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mask: Vec<String> = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| if cell.is_some() { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+
+        let mut board = Board::empty(Shape::from_rows(&mask)?);
+        let height = rows.len();
+        for (index, row) in rows.into_iter().enumerate() {
+            for (col, cell) in row.into_iter().enumerate() {
+                board.set(Pos::new(col, height - 1 - index), cell.flatten());
+            }
+        }
+        board.set_rules(self.rules.clone());
+        board.set_refill(self.refill.clone());
+        Ok(board)
+    }
+}
+
+/// `None` for a hole, `Some(None)` for an empty cell.
+fn parse_cell(symbol: &str) -> Result<Option<Option<Cell>>, String> {
+    match symbol {
+        "." => return Ok(None),
+        "_" => return Ok(Some(None)),
+        _ => {}
+    }
+    let (phase, corrupted) = match symbol.strip_suffix('c') {
+        Some(phase) => (phase, true),
+        None => (symbol, false),
+    };
+    let phase = phase
+        .parse()
+        .map_err(|_| format!("unexpected cell {symbol:?}"))?;
+    Ok(Some(Some(Cell { phase, corrupted })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(board: &[&str]) -> Level {
+        Level {
+            name: "Test".to_string(),
+            board: board.iter().map(|row| row.to_string()).collect(),
+            refill: Refill::Random,
+            rules: RuleSet::default(),
+            move_limit: None,
+        }
+    }
+
+    #[test]
+    fn board_reads_rows_from_the_top() {
+        let board = level(&[". 2c", "_ 0"]).board().unwrap();
+        assert_eq!((board.width(), board.height()), (2, 2));
+        assert!(!board.contains(Pos::new(0, 1)));
+        assert_eq!(
+            board.get(Pos::new(1, 1)),
+            Some(Cell {
+                phase: 2,
+                corrupted: true,
+            })
+        );
+        assert!(board.contains(Pos::new(0, 0)));
+        assert_eq!(board.get(Pos::new(0, 0)), None);
+        assert_eq!(board.get(Pos::new(1, 0)), Some(Cell::default()));
+    }
+
+    #[test]
+    fn board_rejects_bad_cells() {
+        assert!(level(&["0 x"]).board().is_err());
+        assert!(level(&["0 1", "0"]).board().is_err());
+    }
+
+    #[test]
+    fn board_rejects_bad_rules() {
+        let mut level = level(&["0 0"]);
+        level.rules.refill_corruption = 2.0;
+        assert!(level.board().is_err());
+    }
+
+    #[test]
+    fn level_files_parse() {
+        let contents = include_str!("../assets/levels/pocket.level.ron");
+        let level: Level = ron::from_str(contents).unwrap();
+        let board = level.board().unwrap();
+        assert_eq!(board.rules().win_phase, 4);
+        assert!(!board.contains(Pos::new(0, 3)));
+    }
+}
@@ -0,0 +1,18 @@
+//! Bevy-free core of Mergerration, shared by the game and by any tooling
+//! (solvers, simulations, tests) that needs to play by the same rules.
+
+pub mod achievements;
+pub mod campaign;
+pub mod clipboard;
+pub mod daily;
+pub mod highscores;
+pub mod hint;
+pub mod history;
+pub mod level;
+pub mod mode;
+pub mod profile;
+pub mod replay;
+pub mod rules;
+pub mod score;
+pub mod solver;
+pub mod storage;
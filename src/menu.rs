@@ -0,0 +1,311 @@
+//! The main menu and the level select, and the buttons of every menu screen.
+
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+use mergerration::{campaign::MAX_STARS, highscores::Table, mode::Mode, rules::Shape, storage};
+
+use crate::{
+    assets::{
+        level_path, Campaign, CampaignHandle, CampaignLevels, FreePlayRules, Level, PendingLevel,
+    },
+    game::{new_seed, seed_for, GameSet, GameSetup, NewGame, DEFAULT_BOARD_SIZE},
+    in_game,
+    progress::PlayerProfile,
+    recording::{parse_replay, start_playback, ReplayPlayer, REPLAY_KEY},
+    scoreboard::{HighScoreTable, ShownTable},
+    ui::{button_style, clicked, spawn_button},
+    GameState,
+};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ResumeState(GameState::Playing))
+            .add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+            .add_systems(OnExit(GameState::MainMenu), despawn_screen::<MenuScreen>)
+            .add_systems(OnEnter(GameState::LevelSelect), spawn_level_select)
+            .add_systems(OnExit(GameState::LevelSelect), despawn_screen::<MenuScreen>)
+            .add_systems(
+                Update,
+                menu_buttons.run_if(not(in_game)).in_set(GameSet::Request),
+            );
+    }
+}
+
+pub fn spawn_menu_screen(commands: &mut Commands, content: impl FnOnce(&mut ChildBuilder)) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::rgb(0.604, 0.749, 0.784)),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            MenuScreen,
+        ))
+        .with_children(content);
+}
+
+fn spawn_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu_screen(&mut commands, |parent| {
+        parent.spawn(TextBundle::from_section(
+            "Mergerration",
+            TextStyle {
+                font: asset_server.load("Marinda.ttf"),
+                font_size: 80.0,
+                color: Color::WHITE,
+            },
+        ));
+        spawn_button(parent, &asset_server, "PLAY", MenuAction::Play);
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(600.0),
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    column_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                for mode in Mode::ALL {
+                    let label = mode.name().to_uppercase();
+                    spawn_button(parent, &asset_server, &label, MenuAction::NewGame(mode)).insert(
+                        Style {
+                            width: Val::Px(280.0),
+                            ..button_style(65.0)
+                        },
+                    );
+                }
+            });
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(600.0),
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    column_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                for (label, action) in [
+                    ("LEVELS", MenuAction::Levels),
+                    ("HIGH SCORES", MenuAction::HighScores),
+                    ("REPLAY", MenuAction::WatchReplay),
+                ] {
+                    spawn_button(parent, &asset_server, label, action).insert(Style {
+                        width: Val::Px(280.0),
+                        ..button_style(65.0)
+                    });
+                }
+            });
+    });
+}
+
+/// A tile per campaign level, with its stars or a lock.
+fn spawn_level_select(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    campaign_handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+    campaign_levels: Option<Res<CampaignLevels>>,
+    levels: Res<Assets<Level>>,
+    profile: Res<PlayerProfile>,
+) {
+    let campaign = campaigns.get(&campaign_handle.0);
+    spawn_menu_screen(&mut commands, |parent| {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(640.0),
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.0),
+                    column_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                let Some(campaign) = campaign else {
+                    return;
+                };
+                for (index, level) in campaign.levels.iter().enumerate() {
+                    let name = campaign_levels
+                        .as_ref()
+                        .and_then(|handles| levels.get(&handles.0[index]))
+                        .map_or(level.id.as_str(), |level| level.name.as_str());
+                    let (label, action) = if campaign.is_unlocked(index, &profile) {
+                        let stars = profile.stars(&level.id);
+                        (
+                            format!("{}. {name}\n{stars}/{MAX_STARS} stars", index + 1),
+                            MenuAction::Level(index),
+                        )
+                    } else {
+                        (format!("{}. Locked", index + 1), MenuAction::Locked)
+                    };
+                    spawn_button(parent, &asset_server, &label, action).insert(Style {
+                        width: Val::Px(260.0),
+                        ..button_style(120.0)
+                    });
+                }
+            });
+        spawn_button(parent, &asset_server, "BACK", MenuAction::Back);
+    });
+}
+
+fn menu_buttons(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    buttons: Query<(&RelativeCursorPosition, &MenuAction)>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    resume: Res<ResumeState>,
+    mut setup: ResMut<GameSetup>,
+    campaign_handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+    rules: Res<FreePlayRules>,
+    high_scores: Res<HighScoreTable>,
+    mut shown: ResMut<ShownTable>,
+    mut new_games: EventWriter<NewGame>,
+) {
+    let action = buttons
+        .iter()
+        .find(|(button, _)| clicked(button, &mouse_button_input, &touches))
+        .map(|(_, action)| *action)
+        .or_else(|| {
+            (*state.get() != GameState::MainMenu && keyboard_input.just_pressed(KeyCode::Escape))
+                .then_some(MenuAction::Back)
+        });
+    match action {
+        Some(MenuAction::Play) => next_state.set(resume.0),
+        Some(MenuAction::NewGame(mode)) => {
+            commands.remove_resource::<ReplayPlayer>();
+            // Levels go back to the default board, free play keeps its shape.
+            let shape = match setup.level_id {
+                Some(_) => Shape::rect(DEFAULT_BOARD_SIZE, DEFAULT_BOARD_SIZE),
+                None => setup.start.shape().clone(),
+            };
+            *setup = GameSetup::free_play(shape, &rules, mode);
+            new_games.send(NewGame {
+                seed: seed_for(mode),
+            });
+        }
+        Some(MenuAction::Levels) => next_state.set(GameState::LevelSelect),
+        Some(MenuAction::Back) => next_state.set(GameState::MainMenu),
+        Some(MenuAction::Level(index)) => {
+            if let Some(level) = campaigns
+                .get(&campaign_handle.0)
+                .and_then(|campaign| campaign.levels.get(index))
+            {
+                commands.remove_resource::<ReplayPlayer>();
+                commands.insert_resource(PendingLevel {
+                    handle: asset_server.load(level_path(&level.id)),
+                    id: level.id.clone(),
+                    seed: new_seed(),
+                });
+            }
+        }
+        Some(MenuAction::HighScores) => next_state.set(GameState::HighScores),
+        Some(MenuAction::PreviousTable) => {
+            let tables = high_scores.tables.len().max(1);
+            shown.0 = (shown.0 + tables - 1) % tables;
+        }
+        Some(MenuAction::NextTable) => {
+            shown.0 = (shown.0 + 1) % high_scores.tables.len().max(1);
+        }
+        Some(MenuAction::Replay(rank)) => {
+            let Some((table, entries)) = high_scores.tables.iter().nth(shown.0) else {
+                return;
+            };
+            let Some(entry) = entries.get(rank) else {
+                return;
+            };
+            commands.remove_resource::<ReplayPlayer>();
+            match table {
+                Table::Mode(mode) => {
+                    *setup = match &entry.start {
+                        Some(start) => GameSetup {
+                            level: None,
+                            level_id: None,
+                            start: start.clone(),
+                            mode: *mode,
+                            move_limit: mode.move_limit(),
+                        },
+                        None => {
+                            let shape = Shape::rect(DEFAULT_BOARD_SIZE, DEFAULT_BOARD_SIZE);
+                            GameSetup::free_play(shape, &rules, *mode)
+                        }
+                    };
+                    new_games.send(NewGame { seed: entry.seed });
+                }
+                Table::Level(id) => {
+                    commands.insert_resource(PendingLevel {
+                        handle: asset_server.load(level_path(id)),
+                        id: id.clone(),
+                        seed: entry.seed,
+                    });
+                }
+            }
+        }
+        Some(MenuAction::WatchReplay) => {
+            match storage::load(REPLAY_KEY).map(|contents| parse_replay(&contents)) {
+                Some(Ok(replay)) => {
+                    start_playback(&mut commands, &mut setup, &mut new_games, replay)
+                }
+                Some(Err(error)) => warn!("Ignoring the last game's replay: {error}"),
+                None => {}
+            }
+        }
+        Some(MenuAction::Locked) | None => {}
+    }
+}
+
+pub fn despawn_screen<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// In-game state the main menu's PLAY button goes back to.
+#[derive(Resource)]
+pub struct ResumeState(pub GameState);
+
+/// Root of a menu screen, covering the game while it's open.
+#[derive(Component)]
+pub struct MenuScreen;
+
+#[derive(Component, Clone, Copy)]
+pub enum MenuAction {
+    Play,
+    NewGame(Mode),
+    Levels,
+    Back,
+    /// Start the campaign level at this index.
+    Level(usize),
+    Locked,
+    HighScores,
+    PreviousTable,
+    NextTable,
+    /// Play the seed of the shown table's entry at this rank again.
+    Replay(usize),
+    /// Watch the replay of the last game played.
+    WatchReplay,
+}
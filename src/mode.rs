@@ -0,0 +1,61 @@
+//! Ways to play a board: what ends a game and what it is played for.
+
+use serde::{Deserialize, Serialize};
+
+/// Countdown of a timed game.
+pub const TIME_LIMIT_SECONDS: f32 = 120.0;
+/// Merges allowed in a limited moves game.
+pub const MOVE_LIMIT: usize = 30;
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Mode {
+    /// The first winning smiler ends the game.
+    #[default]
+    Classic,
+    /// Normal winning smilers are cashed in for points and the board keeps
+    /// going, until a corrupted one is built.
+    Endless,
+    /// Endless against the clock.
+    Timed,
+    /// Classic with a limited number of merges.
+    LimitedMoves,
+    /// Classic on the default board and rules, seeded from the date so
+    /// everyone gets the same game that day.
+    Daily,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 5] = [
+        Mode::Classic,
+        Mode::Endless,
+        Mode::Timed,
+        Mode::LimitedMoves,
+        Mode::Daily,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Classic => "Classic",
+            Mode::Endless => "Endless",
+            Mode::Timed => "Timed",
+            Mode::LimitedMoves => "Limited moves",
+            Mode::Daily => "Daily",
+        }
+    }
+
+    /// Whether normal winning smilers leave the board for points instead of
+    /// ending the game.
+    pub fn cashes_in(self) -> bool {
+        matches!(self, Mode::Endless | Mode::Timed)
+    }
+
+    pub fn time_limit(self) -> Option<f32> {
+        (self == Mode::Timed).then_some(TIME_LIMIT_SECONDS)
+    }
+
+    pub fn move_limit(self) -> Option<usize> {
+        (self == Mode::LimitedMoves).then_some(MOVE_LIMIT)
+    }
+}
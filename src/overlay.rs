@@ -0,0 +1,115 @@
+//! The solver overlay, for designers checking how winnable a board is.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::Instant};
+use mergerration::solver::Solver;
+use rand::{random, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    board::Board,
+    game::{GameSetup, MoveLog},
+    in_game,
+};
+
+/// Time the solver overlay may take from each frame.
+const SOLVER_FRAME_BUDGET: Duration = Duration::from_millis(4);
+/// Games after which the solver overlay's estimate is good enough.
+const SOLVER_MAX_GAMES: u32 = 20_000;
+
+pub struct SolverOverlayPlugin;
+
+impl Plugin for SolverOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                toggle_solver_overlay,
+                run_solver.run_if(in_game.and_then(resource_exists::<SolverOverlay>)),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// F3 shows or hides the solver overlay.
+fn toggle_solver_overlay(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    overlay: Option<Res<SolverOverlay>>,
+    mut query: Query<&mut Text, With<SolverText>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F3) {
+        return;
+    }
+    if overlay.is_some() {
+        commands.remove_resource::<SolverOverlay>();
+        query.single_mut().sections[0].value.clear();
+    } else {
+        commands.insert_resource(SolverOverlay {
+            solver: None,
+            rng: ChaCha8Rng::seed_from_u64(random()),
+        });
+    }
+}
+
+/// Lets designers check a board is fair: the chance of a normal winning
+/// smiler from it and the merges that lead there, as `(column, row)` from
+/// the bottom left. The search starts over whenever the board changes and
+/// refines a little every frame.
+fn run_solver(
+    board: Res<Board>,
+    setup: Res<GameSetup>,
+    move_log: Res<MoveLog>,
+    mut overlay: ResMut<SolverOverlay>,
+    mut query: Query<&mut Text, With<SolverText>>,
+) {
+    let overlay = &mut *overlay;
+    let move_limit = setup
+        .move_limit
+        .map(|limit| limit.saturating_sub(move_log.merges().len()));
+    let mut current = board.state.clone();
+    current.deselect();
+    let solver = match &mut overlay.solver {
+        Some(solver) if *solver.board() == current => solver,
+        stale => stale.insert(Solver::new(&current, move_limit)),
+    };
+    let start = Instant::now();
+    while solver.games() < SOLVER_MAX_GAMES && start.elapsed() < SOLVER_FRAME_BUDGET {
+        solver.run(1, &mut overlay.rng);
+    }
+
+    let mut moves = solver.moves().to_vec();
+    moves.sort_by(|a, b| b.clean_win_chance().total_cmp(&a.clean_win_chance()));
+    let mut lines = vec![format!(
+        "Solver (F3): {:.0}% clean win chance over {} games",
+        solver.clean_win_chance() * 100.0,
+        solver.games()
+    )];
+    lines.extend(moves.iter().take(3).map(|stats| {
+        format!(
+            "({}, {}) -> ({}, {}): {:.0}% clean, {:.0}% any win",
+            stats.from.col,
+            stats.from.row,
+            stats.to.col,
+            stats.to.row,
+            stats.clean_win_chance() * 100.0,
+            stats.win_chance() * 100.0
+        )
+    }));
+    if moves.is_empty() {
+        lines.push("Nothing can merge".to_string());
+    }
+    query.single_mut().sections[0].value = lines.join("\n");
+}
+
+#[derive(Component)]
+pub struct SolverText;
+
+/// Search behind the solver overlay, while it is shown.
+#[derive(Resource)]
+struct SolverOverlay {
+    solver: Option<Solver>,
+    rng: ChaCha8Rng,
+}
@@ -0,0 +1,111 @@
+//! Player progress kept across sessions: endings and achievements unlocked,
+//! when they were first unlocked, campaign stars, and a few statistics.
+
+use std::{collections::BTreeMap, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+const PROFILE_KEY: &str = "profile";
+
+/// How a game was won.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Ending {
+    Normal,
+    Corrupted,
+}
+
+impl Ending {
+    pub fn new(corrupted: bool) -> Self {
+        if corrupted {
+            Self::Corrupted
+        } else {
+            Self::Normal
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// Unix time in seconds when each ending was first reached.
+    pub endings: BTreeMap<Ending, u64>,
+    /// Unix time in seconds when each achievement, by id, was unlocked.
+    pub achievements: BTreeMap<String, u64>,
+    pub games_played: u32,
+    pub total_merges: u32,
+    /// Mixed merges that rolled a corrupted smiler.
+    pub merges_corrupted: u32,
+    pub smilers_spawned: u32,
+    pub spawned_corrupted: u32,
+    pub hints_used: u32,
+    /// Fewest merges needed to reach each ending.
+    pub best_moves: BTreeMap<Ending, usize>,
+    /// Best stars earned on each campaign level, by level id.
+    pub level_stars: BTreeMap<String, u8>,
+}
+
+impl Profile {
+    /// Reads the stored profile, or starts a new one if there's none. See
+    /// `storage::load_ron` for one that can't be read.
+    pub fn load() -> Result<Self, String> {
+        storage::load_ron(PROFILE_KEY)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let contents = ron::to_string(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        storage::store(PROFILE_KEY, &contents)
+    }
+
+    pub fn has_ending(&self, ending: Ending) -> bool {
+        self.endings.contains_key(&ending)
+    }
+
+    pub fn has_achievement(&self, id: &str) -> bool {
+        self.achievements.contains_key(id)
+    }
+
+    /// Records reaching `ending` in `moves` merges.
+    pub fn reach_ending(&mut self, ending: Ending, moves: usize) {
+        self.endings.entry(ending).or_insert_with(unix_time);
+        let best = self.best_moves.entry(ending).or_insert(moves);
+        *best = (*best).min(moves);
+    }
+
+    pub fn stars(&self, level: &str) -> u8 {
+        self.level_stars.get(level).copied().unwrap_or(0)
+    }
+
+    /// Records finishing `level` with `stars`, returning whether it beats the
+    /// best so far.
+    pub fn record_stars(&mut self, level: &str, stars: u8) -> bool {
+        if stars <= self.stars(level) {
+            return false;
+        }
+        self.level_stars.insert(level.to_string(), stars);
+        true
+    }
+
+    /// Unlocks an achievement, returning whether it wasn't unlocked before.
+    pub fn unlock_achievement(&mut self, id: &str) -> bool {
+        if self.has_achievement(id) {
+            return false;
+        }
+        self.achievements.insert(id.to_string(), unix_time());
+        true
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn unix_time() -> u64 {
+    (web_sys::js_sys::Date::now() / 1000.0) as u64
+}
@@ -0,0 +1,140 @@
+//! What the player earns across games: statistics and achievements, kept in
+//! the profile.
+
+use bevy::prelude::*;
+use mergerration::{
+    achievements::{self, GameEvent},
+    profile::{Ending, Profile},
+};
+use serde::Deserialize;
+
+use crate::{
+    assets::RonLoader,
+    board::Board,
+    game::{GameSet, GameWon, SmilerCorrupted, SmilerMerged, SmilerSpawned},
+    recording::ReplayPlayer,
+    ui::{spawn_toast, ToastArea},
+};
+
+pub struct ProgressPlugin;
+
+impl Plugin for ProgressPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Achievements>()
+            .register_asset_loader(RonLoader::<Achievements>::new(&["achievements.ron"]))
+            .insert_resource(PlayerProfile(Profile::load().unwrap_or_else(|error| {
+                warn!("Starting a new profile, the stored one is unreadable: {error}");
+                Profile::default()
+            })))
+            .add_systems(Startup, load_achievements)
+            .add_systems(
+                Update,
+                (
+                    (update_achievements, update_stats)
+                        .run_if(not(resource_exists::<ReplayPlayer>))
+                        .in_set(GameSet::Outcome),
+                    save_profile,
+                ),
+            );
+    }
+}
+
+/// Lifetime statistics kept in the profile.
+fn update_stats(
+    mut merged: EventReader<SmilerMerged>,
+    mut corrupted: EventReader<SmilerCorrupted>,
+    mut spawned: EventReader<SmilerSpawned>,
+    mut profile: ResMut<PlayerProfile>,
+) {
+    for _ in merged.read() {
+        profile.total_merges += 1;
+    }
+    for _ in corrupted.read() {
+        profile.merges_corrupted += 1;
+    }
+    for event in spawned.read() {
+        profile.smilers_spawned += 1;
+        if event.cell.corrupted {
+            profile.spawned_corrupted += 1;
+        }
+    }
+}
+
+fn save_profile(profile: Res<PlayerProfile>) {
+    if profile.is_changed() && !profile.is_added() {
+        if let Err(error) = profile.save() {
+            warn!("Failed to save the profile: {error}");
+        }
+    }
+}
+
+fn load_achievements(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AchievementsHandle(
+        asset_server.load("game.achievements.ron"),
+    ));
+}
+
+/// Checks achievement triggers against this frame's merges and ending, and
+/// against the board whenever it changes.
+fn update_achievements(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    board: Res<Board>,
+    mut merged: EventReader<SmilerMerged>,
+    mut won: EventReader<GameWon>,
+    achievements_handle: Res<AchievementsHandle>,
+    achievements_assets: Res<Assets<Achievements>>,
+    mut profile: ResMut<PlayerProfile>,
+    toast_area: Query<Entity, With<ToastArea>>,
+) {
+    let Some(registry) = achievements_assets.get(&achievements_handle.0) else {
+        return;
+    };
+
+    let mut events: Vec<_> = merged
+        .read()
+        .map(|event| GameEvent::Merged {
+            merge: event.merge,
+            moves: event.moves,
+        })
+        .collect();
+    events.extend(
+        won.read()
+            .filter(|event| event.pink)
+            .map(|event| GameEvent::EndingReached {
+                ending: Ending::new(event.corrupted),
+                moves: event.moves,
+            }),
+    );
+    if board.is_changed() {
+        events.push(GameEvent::BoardChanged(&board));
+    }
+
+    for event in &events {
+        for achievement in registry.triggered_by(event) {
+            if profile.has_achievement(&achievement.id) {
+                continue;
+            }
+            profile.unlock_achievement(&achievement.id);
+            spawn_toast(
+                &mut commands,
+                toast_area.single(),
+                &asset_server,
+                format!(
+                    "Achievement unlocked: {}\n{}",
+                    achievement.title, achievement.description
+                ),
+            );
+        }
+    }
+}
+
+#[derive(Asset, TypePath, Deref, Deserialize)]
+#[serde(transparent)]
+pub struct Achievements(achievements::Achievements);
+
+#[derive(Resource)]
+pub struct AchievementsHandle(pub Handle<Achievements>);
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct PlayerProfile(Profile);
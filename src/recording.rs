@@ -0,0 +1,285 @@
+//! Recording the game under way into a replay, and playing replays back.
+
+use bevy::prelude::*;
+use mergerration::{
+    replay::{self, Playback, Replay},
+    storage,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::Settled,
+    board::{spawn_smilers, Board},
+    game::{BoardAction, GameRng, GameSet, GameSetup, MoveLog, NewGame},
+    in_game,
+    input::gamepad_just_pressed,
+    launch_option, new_game_requested,
+    save::SAVE_VERSION,
+    ui::{spawn_toast, ToastArea},
+};
+
+pub const REPLAY_KEY: &str = "replay";
+
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recorder>()
+            .add_systems(
+                Startup,
+                (start_recording, play_replay_file)
+                    .chain()
+                    .after(spawn_smilers),
+            )
+            .add_systems(
+                Update,
+                (
+                    play_replay
+                        .run_if(in_game.and_then(resource_exists::<ReplayPlayer>))
+                        .before(GameSet::Act)
+                        .before(GameSet::Start),
+                    record_actions.run_if(in_game).after(GameSet::Act),
+                    record_new_game.after(GameSet::Start),
+                    save_replay,
+                ),
+            );
+    }
+}
+
+/// Keeps recording the resumed game if the stored replay is of it, or starts
+/// recording a fresh one.
+fn start_recording(
+    board: Res<Board>,
+    setup: Res<GameSetup>,
+    rng: Res<GameRng>,
+    move_log: Res<MoveLog>,
+    mut recorder: ResMut<Recorder>,
+) {
+    let stored = storage::load(REPLAY_KEY)
+        .and_then(|contents| parse_replay(&contents).ok())
+        .filter(|replay| {
+            !new_game_requested() && replay.setup == *setup && replay.seed == rng.seed
+        });
+    recorder.0 = stored.or_else(|| {
+        (move_log.merges().is_empty() && board.state == setup.start)
+            .then(|| Replay::new(setup.clone(), rng.seed))
+    });
+}
+
+pub fn parse_replay(contents: &str) -> Result<Replay<GameSetup>, String> {
+    let file: ReplayFile = ron::from_str(contents).map_err(|error| error.to_string())?;
+    if file.version != SAVE_VERSION {
+        return Err(format!("unsupported version {}", file.version));
+    }
+    file.replay.setup.start.validate()?;
+    Ok(file.replay)
+}
+
+/// `--replay <file>` plays back a replay file, such as the `replay.ron` the
+/// game keeps of the last game next to its save.
+fn play_replay_file(
+    mut commands: Commands,
+    mut setup: ResMut<GameSetup>,
+    mut new_games: EventWriter<NewGame>,
+) {
+    let Some(path) = launch_option("replay") else {
+        return;
+    };
+    match read_replay_file(&path).and_then(|contents| parse_replay(&contents)) {
+        Ok(replay) => start_playback(&mut commands, &mut setup, &mut new_games, replay),
+        Err(error) => warn!("Ignoring replay {path:?}: {error}"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_replay_file(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|error| error.to_string())
+}
+
+/// There are no files on the web, so `?replay=<anything>` plays the last
+/// game instead.
+#[cfg(target_arch = "wasm32")]
+fn read_replay_file(_path: &str) -> Result<String, String> {
+    storage::load(REPLAY_KEY).ok_or_else(|| "no game recorded yet".to_string())
+}
+
+pub fn start_playback(
+    commands: &mut Commands,
+    setup: &mut GameSetup,
+    new_games: &mut EventWriter<NewGame>,
+    replay: Replay<GameSetup>,
+) {
+    *setup = replay.setup.clone();
+    new_games.send(NewGame { seed: replay.seed });
+    commands.insert_resource(ReplayPlayer(Playback::new(replay)));
+}
+
+/// Drives the game from the replay being played. Space or the south face
+/// button pause, Right or the D-pad step through single actions, Up and
+/// Down change the speed, and Escape or the east face button stop. Once
+/// stopped or over, the game goes on from there.
+pub fn play_replay(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut player: ResMut<ReplayPlayer>,
+    mut recorder: ResMut<Recorder>,
+    mut actions: EventWriter<BoardAction>,
+    mut new_games: EventWriter<NewGame>,
+    settled: Res<Settled>,
+    toast_area: Query<Entity, With<ToastArea>>,
+) {
+    let pressed = |keys: [KeyCode; 2], button_type: GamepadButtonType| {
+        keyboard_input.any_just_pressed(keys) || gamepad_just_pressed(&gamepad_buttons, button_type)
+    };
+    let stopped = pressed(
+        [KeyCode::Escape, KeyCode::Backspace],
+        GamepadButtonType::East,
+    );
+    if stopped || player.is_finished() {
+        recorder.0 = Some(player.played());
+        commands.remove_resource::<ReplayPlayer>();
+        let message = if stopped {
+            "Replay stopped, your turn"
+        } else {
+            "Replay over, your turn"
+        };
+        spawn_toast(
+            &mut commands,
+            toast_area.single(),
+            &asset_server,
+            message.to_string(),
+        );
+        return;
+    }
+    if pressed([KeyCode::Space, KeyCode::KeyP], GamepadButtonType::South) {
+        player.toggle_pause();
+    }
+    if pressed(
+        [KeyCode::ArrowUp, KeyCode::Equal],
+        GamepadButtonType::DPadUp,
+    ) {
+        player.faster();
+    }
+    if pressed(
+        [KeyCode::ArrowDown, KeyCode::Minus],
+        GamepadButtonType::DPadDown,
+    ) {
+        player.slower();
+    }
+    // The recording stopped its clock while the board settled, so does the
+    // playback.
+    let due = if !settled.0 {
+        Vec::new()
+    } else if pressed(
+        [KeyCode::ArrowRight, KeyCode::Period],
+        GamepadButtonType::DPadRight,
+    ) {
+        player.step().into_iter().collect()
+    } else {
+        player.advance(time.delta_seconds())
+    };
+    for action in due {
+        let action = match action {
+            replay::Action::Select(pos) => BoardAction::Select(pos),
+            replay::Action::Merge { from, to } => BoardAction::Merge { from, to },
+            replay::Action::Deselect => BoardAction::Deselect,
+            replay::Action::Undo => BoardAction::Undo,
+            replay::Action::Redo => BoardAction::Redo,
+            replay::Action::Shuffle => BoardAction::Shuffle,
+            replay::Action::Restart { seed } => {
+                new_games.send(NewGame { seed });
+                continue;
+            }
+        };
+        actions.send(action);
+    }
+}
+
+/// Records board actions into the replay of the game under way.
+fn record_actions(
+    time: Res<Time>,
+    player: Option<Res<ReplayPlayer>>,
+    settled: Res<Settled>,
+    mut actions: EventReader<BoardAction>,
+    mut recorder: ResMut<Recorder>,
+) {
+    if player.is_some() {
+        actions.clear();
+        return;
+    }
+    // Playback waits for the board to settle anyway, so only the time the
+    // board was still counts. Time passing alone isn't worth saving the
+    // replay for.
+    if let Some(replay) = &mut recorder.bypass_change_detection().0 {
+        if settled.0 {
+            replay.tick(time.delta_seconds());
+        }
+    }
+    for action in actions.read() {
+        if let Some(replay) = &mut recorder.0 {
+            replay.record((*action).into());
+        }
+    }
+}
+
+/// Keeps restarts in the recording, while a game on another setup starts a
+/// new one.
+fn record_new_game(
+    mut new_games: EventReader<NewGame>,
+    setup: Res<GameSetup>,
+    player: Option<Res<ReplayPlayer>>,
+    mut recorder: ResMut<Recorder>,
+) {
+    let Some(new_game) = new_games.read().last() else {
+        return;
+    };
+    if player.is_some() {
+        return;
+    }
+    match &mut recorder.0 {
+        Some(replay) if replay.setup == *setup => replay.record(replay::Action::Restart {
+            seed: new_game.seed,
+        }),
+        recording => *recording = Some(Replay::new(setup.clone(), new_game.seed)),
+    }
+}
+
+fn save_replay(recorder: Res<Recorder>) {
+    if !recorder.is_changed() || recorder.is_added() {
+        return;
+    }
+    let Some(replay) = &recorder.0 else {
+        return;
+    };
+    let file = ReplayFile {
+        version: SAVE_VERSION,
+        replay: replay.clone(),
+    };
+    let result = ron::to_string(&file)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| {
+            storage::store(REPLAY_KEY, &contents).map_err(|error| error.to_string())
+        });
+    if let Err(error) = result {
+        warn!("Failed to save the replay: {error}");
+    }
+}
+
+/// Replay of the game under way, if it was recorded from its start.
+#[derive(Resource, Default)]
+pub struct Recorder(Option<Replay<GameSetup>>);
+
+/// Replay being played back instead of taking the player's input.
+#[derive(Resource, Deref, DerefMut)]
+pub struct ReplayPlayer(Playback<GameSetup>);
+
+/// A replay as stored. It holds a `GameSetup`, so it follows the save
+/// format's version.
+#[derive(Serialize, Deserialize)]
+struct ReplayFile {
+    version: u32,
+    replay: Replay<GameSetup>,
+}
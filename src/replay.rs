@@ -0,0 +1,235 @@
+//! Recorded games: every action the player took and when, from a known
+//! setup and seed, so the game can play them back through the very same
+//! systems.
+
+use serde::{Deserialize, Serialize};
+
+use crate::rules::Pos;
+
+/// Playback speeds to pick from, the normal one at `NORMAL_SPEED`.
+pub const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+
+/// An input-level action, whatever device it came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Select(Pos),
+    /// Two smilers merged directly, ignoring the selection.
+    Merge {
+        from: Pos,
+        to: Pos,
+    },
+    Deselect,
+    Undo,
+    Redo,
+    /// The stuck board rearranged by the shuffle power-up.
+    Shuffle,
+    /// A new game on the same setup.
+    Restart {
+        seed: u64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    /// Seconds of play since the recording started.
+    pub time: f32,
+    pub action: Action,
+}
+
+/// Everything needed to play a recorded game again. `S` is whatever the
+/// game needs to set up the starting board.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay<S> {
+    pub setup: S,
+    pub seed: u64,
+    pub steps: Vec<Step>,
+    /// Seconds of play recorded so far.
+    pub length: f32,
+}
+
+impl<S> Replay<S> {
+    pub fn new(setup: S, seed: u64) -> Self {
+        Self {
+            setup,
+            seed,
+            steps: Vec::new(),
+            length: 0.0,
+        }
+    }
+
+    /// Lets `seconds` of play go by.
+    pub fn tick(&mut self, seconds: f32) {
+        self.length += seconds;
+    }
+
+    /// Records `action` as happening now.
+    pub fn record(&mut self, action: Action) {
+        self.steps.push(Step {
+            time: self.length,
+            action,
+        });
+    }
+}
+
+/// Plays a replay back, with pause, single steps and speed control.
+#[derive(Clone, Debug)]
+pub struct Playback<S> {
+    replay: Replay<S>,
+    /// Index of the next step to play.
+    next: usize,
+    clock: f32,
+    speed: usize,
+    paused: bool,
+}
+
+impl<S> Playback<S> {
+    pub fn new(replay: Replay<S>) -> Self {
+        Self {
+            replay,
+            next: 0,
+            clock: 0.0,
+            speed: NORMAL_SPEED,
+            paused: false,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay<S> {
+        &self.replay
+    }
+
+    /// Lets `seconds` of real time go by and returns the actions due. A
+    /// restart is always the last action returned, so the new game is set
+    /// up before anything is played on it.
+    pub fn advance(&mut self, seconds: f32) -> Vec<Action> {
+        if self.paused {
+            return Vec::new();
+        }
+        self.clock = (self.clock + seconds * self.speed()).min(self.replay.length);
+        let mut actions = Vec::new();
+        while let Some(step) = self.replay.steps.get(self.next) {
+            if step.time > self.clock {
+                break;
+            }
+            self.next += 1;
+            actions.push(step.action);
+            if matches!(step.action, Action::Restart { .. }) {
+                // Whatever else is due waits for the next call.
+                self.clock = step.time;
+                break;
+            }
+        }
+        actions
+    }
+
+    /// Pauses and plays just the next action.
+    pub fn step(&mut self) -> Option<Action> {
+        self.paused = true;
+        let step = self.replay.steps.get(self.next)?;
+        self.next += 1;
+        self.clock = step.time;
+        Some(step.action)
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn speed(&self) -> f32 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    /// Every action was played and the recording's time ran out.
+    pub fn is_finished(&self) -> bool {
+        self.next == self.replay.steps.len() && self.clock >= self.replay.length
+    }
+
+    /// The part of the replay played so far, to keep recording from.
+    pub fn played(&self) -> Replay<S>
+    where
+        S: Clone,
+    {
+        Replay {
+            setup: self.replay.setup.clone(),
+            seed: self.replay.seed,
+            steps: self.replay.steps[..self.next].to_vec(),
+            length: self.clock,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Selects at 1s, restarts at 2s and deselects at 2.5s, 4s long.
+    fn replay() -> Replay<()> {
+        let mut replay = Replay::new((), 1);
+        replay.tick(1.0);
+        replay.record(Action::Select(Pos::new(0, 0)));
+        replay.tick(1.0);
+        replay.record(Action::Restart { seed: 2 });
+        replay.tick(0.5);
+        replay.record(Action::Deselect);
+        replay.tick(1.5);
+        replay
+    }
+
+    #[test]
+    fn advance_plays_what_is_due() {
+        let mut playback = Playback::new(replay());
+        assert!(playback.advance(0.5).is_empty());
+        assert_eq!(playback.advance(0.5), vec![Action::Select(Pos::new(0, 0))]);
+        playback.faster();
+        assert_eq!(playback.speed(), 2.0);
+        assert_eq!(playback.advance(0.5), vec![Action::Restart { seed: 2 }]);
+    }
+
+    #[test]
+    fn advance_stops_after_a_restart() {
+        let mut playback = Playback::new(replay());
+        assert_eq!(
+            playback.advance(10.0),
+            vec![Action::Select(Pos::new(0, 0)), Action::Restart { seed: 2 },]
+        );
+        assert!(!playback.is_finished());
+        assert_eq!(playback.advance(0.5), vec![Action::Deselect]);
+        playback.advance(10.0);
+        assert!(playback.is_finished());
+    }
+
+    #[test]
+    fn paused_playback_waits() {
+        let mut playback = Playback::new(replay());
+        playback.toggle_pause();
+        assert!(playback.advance(10.0).is_empty());
+        playback.toggle_pause();
+        assert!(!playback.is_paused());
+        assert_eq!(playback.advance(1.0).len(), 1);
+    }
+
+    #[test]
+    fn step_plays_one_action_and_pauses() {
+        let mut playback = Playback::new(replay());
+        assert_eq!(playback.step(), Some(Action::Select(Pos::new(0, 0))));
+        assert!(playback.is_paused());
+        let played = playback.played();
+        assert_eq!(played.steps.len(), 1);
+        assert_eq!(played.length, 1.0);
+        playback.step();
+        playback.step();
+        assert_eq!(playback.step(), None);
+    }
+}
@@ -0,0 +1,683 @@
+//! Merge, corruption, gravity and refill rules on a plain grid of cells.
+
+use std::collections::VecDeque;
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+/// Phase that ends the game once a smiler reaches it, by default.
+pub const WIN_PHASE: u8 = 5;
+/// Default chance that merging a normal and a corrupted smiler gives a
+/// corrupted one.
+pub const MIXED_MERGE_CORRUPTION: f64 = 0.9;
+/// Default chance that a smiler dropped in from the top is corrupted.
+pub const REFILL_CORRUPTION: f64 = 0.7;
+/// Arrangements `Board::shuffle` tries before giving up.
+pub const SHUFFLE_ATTEMPTS: usize = 100;
+
+/// Cell coordinates, `(0, 0)` being the bottom left cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Pos {
+    pub col: usize,
+    pub row: usize,
+}
+
+impl Pos {
+    pub fn new(col: usize, row: usize) -> Self {
+        Self { col, row }
+    }
+
+    /// Cells touching each other, diagonals included.
+    pub fn is_neighbor(self, other: Pos) -> bool {
+        self != other && self.col.abs_diff(other.col) <= 1 && self.row.abs_diff(other.row) <= 1
+    }
+
+    /// Cells sharing a side.
+    pub fn is_orthogonal_neighbor(self, other: Pos) -> bool {
+        self.col.abs_diff(other.col) + self.row.abs_diff(other.row) == 1
+    }
+}
+
+/// A single smiler.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Cell {
+    pub phase: u8,
+    pub corrupted: bool,
+}
+
+/// Result of merging the smiler at `from` into the one at `to`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Merge {
+    pub from: Pos,
+    pub to: Pos,
+    /// The smiler now standing at `to`.
+    pub cell: Cell,
+    /// Whether exactly one of the merged smilers was corrupted.
+    pub mixed: bool,
+}
+
+/// A smiler moved down by gravity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fall {
+    pub from: Pos,
+    pub to: Pos,
+}
+
+/// Size of a board and which of its cells can hold smilers.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Shape {
+    width: usize,
+    height: usize,
+    playable: Vec<bool>,
+}
+
+impl Shape {
+    pub fn rect(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            playable: vec![true; width * height],
+        }
+    }
+
+    /// Parses rows drawn from top to bottom, `#` being a playable cell and
+    /// `.` a hole.
+    pub fn from_rows<S: AsRef<str>>(rows: &[S]) -> Result<Self, String> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.as_ref().chars().count());
+        if width == 0 {
+            return Err("a board needs at least one cell".to_string());
+        }
+        let mut shape = Self::rect(width, height);
+        for (index, row) in rows.iter().enumerate() {
+            let row = row.as_ref();
+            if row.chars().count() != width {
+                return Err(format!("row {row:?} is not {width} cells wide"));
+            }
+            for (col, symbol) in row.chars().enumerate() {
+                let playable = match symbol {
+                    '#' => true,
+                    '.' => false,
+                    _ => return Err(format!("unexpected {symbol:?} in row {row:?}")),
+                };
+                shape.playable[(height - 1 - index) * width + col] = playable;
+            }
+        }
+        Ok(shape)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        pos.col < self.width && pos.row < self.height && self.playable[self.index(pos)]
+    }
+
+    /// Playable cells, row by row from the bottom.
+    pub fn positions(&self) -> impl Iterator<Item = Pos> + '_ {
+        (0..self.width * self.height)
+            .map(|index| Pos::new(index % self.width, index / self.width))
+            .filter(|pos| self.contains(*pos))
+    }
+
+    fn index(&self, pos: Pos) -> usize {
+        pos.row * self.width + pos.col
+    }
+}
+
+/// Which cells count as neighbors for merges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Adjacency {
+    /// Cells sharing a side.
+    Orthogonal,
+    /// Cells sharing a side or a corner.
+    #[default]
+    Diagonal,
+}
+
+impl Adjacency {
+    pub fn connects(self, a: Pos, b: Pos) -> bool {
+        match self {
+            Self::Orthogonal => a.is_orthogonal_neighbor(b),
+            Self::Diagonal => a.is_neighbor(b),
+        }
+    }
+}
+
+/// Tunable rules of a game.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSet {
+    pub adjacency: Adjacency,
+    pub win_phase: u8,
+    /// Chance that a mixed merge gives a corrupted smiler, by the phase of
+    /// the merged smilers. The last entry covers all higher phases.
+    pub mixed_merge_corruption: Vec<f64>,
+    /// Chance that a smiler dropped in from the top is corrupted.
+    pub refill_corruption: f64,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            adjacency: Adjacency::default(),
+            win_phase: WIN_PHASE,
+            mixed_merge_corruption: vec![MIXED_MERGE_CORRUPTION],
+            refill_corruption: REFILL_CORRUPTION,
+        }
+    }
+}
+
+impl RuleSet {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.win_phase == 0 {
+            return Err("the win phase must be at least 1".to_string());
+        }
+        if self.mixed_merge_corruption.is_empty() {
+            return Err("mixed merge corruption needs at least one chance".to_string());
+        }
+        let chances = self
+            .mixed_merge_corruption
+            .iter()
+            .chain([&self.refill_corruption]);
+        for chance in chances {
+            if !(0.0..=1.0).contains(chance) {
+                return Err(format!("{chance} is not a chance between 0 and 1"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Chance that a mixed merge of two smilers of `phase` comes out
+    /// corrupted.
+    pub fn mixed_merge_corruption(&self, phase: u8) -> f64 {
+        let chances = &self.mixed_merge_corruption;
+        chances[usize::from(phase).min(chances.len() - 1)]
+    }
+}
+
+/// Where the smilers filling empty cells come from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Refill {
+    /// Phase 0 smilers, corrupted at the rules' refill odds.
+    #[default]
+    Random,
+    /// Exactly these smilers, in order. Once they run out, empty cells stay
+    /// empty.
+    Queue(VecDeque<Cell>),
+}
+
+/// What happened after a click on a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+    Selected(Pos),
+    Deselected(Pos),
+    Merged(Merge),
+    Ignored,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Board {
+    shape: Shape,
+    cells: Vec<Option<Cell>>,
+    selected: Option<Pos>,
+    rules: RuleSet,
+    refill: Refill,
+}
+
+impl Board {
+    /// Starting board: every cell holds a normal phase 0 smiler.
+    pub fn new(shape: Shape) -> Self {
+        let mut board = Self::empty(shape);
+        for pos in board.positions().collect::<Vec<_>>() {
+            board.set(pos, Some(Cell::default()));
+        }
+        board
+    }
+
+    pub fn empty(shape: Shape) -> Self {
+        Self {
+            cells: vec![None; shape.width * shape.height],
+            shape,
+            selected: None,
+            rules: RuleSet::default(),
+            refill: Refill::default(),
+        }
+    }
+
+    /// Checks what deserializing a board can't: that its cells fit its
+    /// shape and its rules make sense.
+    pub fn validate(&self) -> Result<(), String> {
+        let size = self.width() * self.height();
+        if size == 0 {
+            return Err("a board needs at least one cell".to_string());
+        }
+        if self.shape.playable.len() != size || self.cells.len() != size {
+            return Err(format!(
+                "cells don't fit a {}x{} board",
+                self.width(),
+                self.height()
+            ));
+        }
+        if self.selected.is_some_and(|pos| !self.contains(pos)) {
+            return Err("the selected cell is off the board".to_string());
+        }
+        self.rules.validate()
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+    }
+
+    pub fn set_refill(&mut self, refill: Refill) {
+        self.refill = refill;
+    }
+
+    /// Whether `merge` built a smiler of the winning phase.
+    pub fn wins(&self, merge: &Merge) -> bool {
+        merge.cell.phase >= self.rules.win_phase
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    pub fn width(&self) -> usize {
+        self.shape.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.shape.height
+    }
+
+    /// Whether `pos` is a playable cell of the board.
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.shape.contains(pos)
+    }
+
+    pub fn get(&self, pos: Pos) -> Option<Cell> {
+        if self.contains(pos) {
+            self.cells[self.shape.index(pos)]
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, pos: Pos, cell: Option<Cell>) {
+        if self.contains(pos) {
+            let index = self.shape.index(pos);
+            self.cells[index] = cell;
+        }
+    }
+
+    /// Playable cells of the board, row by row from the bottom.
+    pub fn positions(&self) -> impl Iterator<Item = Pos> + '_ {
+        self.shape.positions()
+    }
+
+    /// Playable cells next to `pos` by the board's adjacency, row by row
+    /// from the bottom.
+    pub fn neighbors(&self, pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        let rows = pos.row.saturating_sub(1)..=pos.row + 1;
+        rows.flat_map(move |row| {
+            (pos.col.saturating_sub(1)..=pos.col + 1).map(move |col| Pos::new(col, row))
+        })
+        .filter(move |other| self.contains(*other) && self.rules.adjacency.connects(pos, *other))
+    }
+
+    pub fn corrupted_neighbors(&self, pos: Pos) -> usize {
+        self.neighbors(pos)
+            .filter(|neighbor| self.get(*neighbor).is_some_and(|cell| cell.corrupted))
+            .count()
+    }
+
+    pub fn all_corrupted(&self) -> bool {
+        self.cells.iter().flatten().all(|cell| cell.corrupted)
+    }
+
+    pub fn selected(&self) -> Option<Pos> {
+        self.selected
+    }
+
+    pub fn deselect(&mut self) {
+        self.selected = None;
+    }
+
+    /// Handles a click on `pos`: selects it, drops the selection, or merges
+    /// the selected smiler into this one.
+    pub fn select<R: Rng + ?Sized>(&mut self, pos: Pos, rng: &mut R) -> Selection {
+        if self.get(pos).is_none() {
+            return Selection::Ignored;
+        }
+        match self.selected {
+            None => {
+                self.selected = Some(pos);
+                Selection::Selected(pos)
+            }
+            Some(selected) if selected == pos => {
+                self.selected = None;
+                Selection::Deselected(pos)
+            }
+            Some(selected) => match self.merge(selected, pos, rng) {
+                Some(merge) => Selection::Merged(merge),
+                None => Selection::Ignored,
+            },
+        }
+    }
+
+    /// Every legal merge as `(from, to)`, both ways round.
+    pub fn merges(&self) -> impl Iterator<Item = (Pos, Pos)> + '_ {
+        self.positions().flat_map(move |from| {
+            self.neighbors(from)
+                .filter(move |to| self.can_merge(from, *to))
+                .map(move |to| (from, to))
+        })
+    }
+
+    /// Whether any two smilers can merge.
+    pub fn has_merges(&self) -> bool {
+        self.merges().next().is_some()
+    }
+
+    pub fn can_merge(&self, from: Pos, to: Pos) -> bool {
+        match (self.get(from), self.get(to)) {
+            (Some(a), Some(b)) => a.phase == b.phase && self.rules.adjacency.connects(from, to),
+            _ => false,
+        }
+    }
+
+    /// Merges the smiler at `from` into the one at `to`, leaving `from` empty.
+    /// A mixed merge rolls whether the result is corrupted.
+    pub fn merge<R: Rng + ?Sized>(&mut self, from: Pos, to: Pos, rng: &mut R) -> Option<Merge> {
+        if !self.can_merge(from, to) {
+            return None;
+        }
+        let source = self.get(from)?;
+        let mut cell = self.get(to)?;
+        let mixed = source.corrupted != cell.corrupted;
+
+        if mixed {
+            cell.corrupted = rng.gen::<f64>() < self.rules.mixed_merge_corruption(cell.phase);
+        }
+        cell.phase += 1;
+        self.set(from, None);
+        self.set(to, Some(cell));
+        self.selected = None;
+
+        Some(Merge {
+            from,
+            to,
+            cell,
+            mixed,
+        })
+    }
+
+    /// Drops every smiler down as far as its column allows. Smilers fall
+    /// past holes to the next playable cell.
+    pub fn apply_gravity(&mut self) -> Vec<Fall> {
+        let mut falls = Vec::new();
+        for col in 0..self.width() {
+            let column: Vec<_> = (0..self.height())
+                .map(|row| Pos::new(col, row))
+                .filter(|pos| self.contains(*pos))
+                .collect();
+            let mut landed = 0;
+            for &from in &column {
+                if let Some(cell) = self.get(from) {
+                    let to = column[landed];
+                    landed += 1;
+                    if from != to {
+                        self.set(from, None);
+                        self.set(to, Some(cell));
+                        falls.push(Fall { from, to });
+                    }
+                }
+            }
+        }
+        falls
+    }
+
+    /// Rearranges the smilers at random and lets them fall, trying a few
+    /// arrangements for one where two smilers can merge. Returns whether it
+    /// found one; if not, the board is left as it was.
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        let positions: Vec<_> = self.positions().collect();
+        for _ in 0..SHUFFLE_ATTEMPTS {
+            let mut cells: Vec<_> = positions.iter().map(|pos| self.get(*pos)).collect();
+            cells.shuffle(rng);
+            let mut board = self.clone();
+            for (pos, cell) in positions.iter().zip(cells) {
+                board.set(*pos, cell);
+            }
+            board.apply_gravity();
+            board.selected = None;
+            if board.has_merges() {
+                *self = board;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Fills the empty cells, bottom up in each column, and returns what was
+    /// spawned where.
+    pub fn refill<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Vec<(Pos, Cell)> {
+        let mut spawned = Vec::new();
+        for col in 0..self.width() {
+            for row in 0..self.height() {
+                let pos = Pos::new(col, row);
+                if !self.contains(pos) || self.get(pos).is_some() {
+                    continue;
+                }
+                let cell = match &mut self.refill {
+                    Refill::Random => Cell {
+                        phase: 0,
+                        corrupted: rng.gen::<f64>() < self.rules.refill_corruption,
+                    },
+                    Refill::Queue(queue) => match queue.pop_front() {
+                        Some(cell) => cell,
+                        None => return spawned,
+                    },
+                };
+                self.set(pos, Some(cell));
+                spawned.push((pos, cell));
+            }
+        }
+        spawned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    fn rng() -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(7)
+    }
+
+    fn cell(phase: u8) -> Option<Cell> {
+        Some(Cell {
+            phase,
+            corrupted: false,
+        })
+    }
+
+    fn corrupted(phase: u8) -> Option<Cell> {
+        Some(Cell {
+            phase,
+            corrupted: true,
+        })
+    }
+
+    /// A 2x2 board, cells listed row by row from the bottom.
+    fn square(cells: [Option<Cell>; 4], adjacency: Adjacency) -> Board {
+        let mut board = Board::empty(Shape::rect(2, 2));
+        for (index, cell) in cells.into_iter().enumerate() {
+            board.set(Pos::new(index % 2, index / 2), cell);
+        }
+        board.set_rules(RuleSet {
+            adjacency,
+            ..RuleSet::default()
+        });
+        board
+    }
+
+    #[test]
+    fn merge_matching_phases() {
+        for adjacency in [Adjacency::Orthogonal, Adjacency::Diagonal] {
+            let mut board = square([cell(0), cell(0), cell(1), cell(1)], adjacency);
+            let merge = board.merge(Pos::new(0, 0), Pos::new(1, 0), &mut rng());
+            assert_eq!(
+                merge,
+                Some(Merge {
+                    from: Pos::new(0, 0),
+                    to: Pos::new(1, 0),
+                    cell: cell(1).unwrap(),
+                    mixed: false,
+                })
+            );
+            assert_eq!(board.get(Pos::new(0, 0)), None);
+            assert_eq!(board.get(Pos::new(1, 0)), cell(1));
+        }
+    }
+
+    #[test]
+    fn merge_rejects_other_phases() {
+        for adjacency in [Adjacency::Orthogonal, Adjacency::Diagonal] {
+            let mut board = square([cell(0), cell(1), cell(1), cell(2)], adjacency);
+            let before = board.clone();
+            assert_eq!(
+                board.merge(Pos::new(0, 0), Pos::new(1, 0), &mut rng()),
+                None
+            );
+            assert_eq!(board, before);
+        }
+    }
+
+    #[test]
+    fn merge_diagonally_by_adjacency() {
+        let cells = [cell(0), cell(1), cell(2), cell(0)];
+        let mut orthogonal = square(cells, Adjacency::Orthogonal);
+        assert_eq!(
+            orthogonal.merge(Pos::new(0, 0), Pos::new(1, 1), &mut rng()),
+            None
+        );
+        let mut diagonal = square(cells, Adjacency::Diagonal);
+        assert!(diagonal
+            .merge(Pos::new(0, 0), Pos::new(1, 1), &mut rng())
+            .is_some());
+    }
+
+    #[test]
+    fn mixed_merge_rolls_corruption() {
+        let mixed = |chance| {
+            let mut board = square(
+                [cell(0), corrupted(0), cell(1), cell(2)],
+                Adjacency::Diagonal,
+            );
+            board.rules.mixed_merge_corruption = vec![chance];
+            board
+                .merge(Pos::new(0, 0), Pos::new(1, 0), &mut rng())
+                .unwrap()
+        };
+        assert!(mixed(1.0).cell.corrupted);
+        assert!(!mixed(0.0).cell.corrupted);
+        assert!(mixed(0.5).mixed);
+        // The same seed rolls the same way.
+        assert_eq!(mixed(0.5), mixed(0.5));
+    }
+
+    #[test]
+    fn gravity_falls_past_holes() {
+        let shape = Shape::from_rows(&["#", ".", "#"]).unwrap();
+        let mut board = Board::empty(shape);
+        board.set(Pos::new(0, 2), cell(3));
+        let falls = board.apply_gravity();
+        assert_eq!(
+            falls,
+            vec![Fall {
+                from: Pos::new(0, 2),
+                to: Pos::new(0, 0),
+            }]
+        );
+        assert_eq!(board.get(Pos::new(0, 0)), cell(3));
+        assert_eq!(board.get(Pos::new(0, 2)), None);
+    }
+
+    #[test]
+    fn refill_random() {
+        let mut board = square([cell(2), None, cell(2), None], Adjacency::Diagonal);
+        board.rules.refill_corruption = 0.0;
+        let spawned = board.refill(&mut rng());
+        assert_eq!(
+            spawned,
+            vec![
+                (Pos::new(1, 0), cell(0).unwrap()),
+                (Pos::new(1, 1), cell(0).unwrap()),
+            ]
+        );
+        assert!(board.positions().all(|pos| board.get(pos).is_some()));
+    }
+
+    #[test]
+    fn refill_queue_runs_dry() {
+        let mut board = square([cell(2), None, cell(2), None], Adjacency::Diagonal);
+        board.set_refill(Refill::Queue(VecDeque::from([corrupted(4).unwrap()])));
+        let spawned = board.refill(&mut rng());
+        assert_eq!(spawned, vec![(Pos::new(1, 0), corrupted(4).unwrap())]);
+        assert_eq!(board.get(Pos::new(1, 1)), None);
+        assert!(board.refill(&mut rng()).is_empty());
+    }
+
+    #[test]
+    fn validate_catches_broken_boards() {
+        let mut board = Board::new(Shape::rect(2, 2));
+        assert_eq!(board.validate(), Ok(()));
+        board.cells.pop();
+        assert!(board.validate().is_err());
+        let mut board = Board::new(Shape::rect(2, 2));
+        board.rules.mixed_merge_corruption.clear();
+        assert!(board.validate().is_err());
+    }
+
+    #[test]
+    fn has_merges() {
+        assert!(Board::new(Shape::rect(2, 2)).has_merges());
+        let stuck = square([cell(0), cell(1), cell(2), cell(3)], Adjacency::Diagonal);
+        assert!(!stuck.has_merges());
+    }
+
+    #[test]
+    fn shuffle_finds_a_merge() {
+        let mut board = square([cell(0), cell(1), cell(2), cell(0)], Adjacency::Orthogonal);
+        assert!(!board.has_merges());
+        assert!(board.shuffle(&mut rng()));
+        assert!(board.has_merges());
+        let mut phases: Vec<_> = board
+            .positions()
+            .map(|pos| board.get(pos).unwrap().phase)
+            .collect();
+        phases.sort();
+        assert_eq!(phases, vec![0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn shuffle_gives_up_without_a_pair() {
+        let mut board = square([cell(0), cell(1), cell(2), cell(3)], Adjacency::Diagonal);
+        let before = board.clone();
+        assert!(!board.shuffle(&mut rng()));
+        assert_eq!(board, before);
+    }
+}
@@ -0,0 +1,131 @@
+//! Saving the game under way, to resume it on the next launch.
+
+use bevy::prelude::*;
+use mergerration::{history::History, rules, storage};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::Board,
+    game::{configure_board, GameInfo, GameRng, GameSet, GameSetup, MoveLog},
+    new_game_requested,
+    recording::ReplayPlayer,
+    GameState,
+};
+
+const SAVE_KEY: &str = "save";
+pub const SAVE_VERSION: u32 = 10;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_game.after(configure_board))
+            .add_systems(
+                Update,
+                autosave
+                    .after(GameSet::Resolve)
+                    .run_if(not(resource_exists::<ReplayPlayer>)),
+            );
+    }
+}
+
+/// Resumes the saved game, unless launch options ask for a new one.
+pub fn load_game(
+    mut board: ResMut<Board>,
+    mut rng: ResMut<GameRng>,
+    mut move_log: ResMut<MoveLog>,
+    mut game_info: ResMut<GameInfo>,
+    mut setup: ResMut<GameSetup>,
+) {
+    let save = read_save().filter(|_| !new_game_requested());
+    if let Some(save) = &save {
+        // Only the last ending carries over into a new game.
+        game_info.current_win_corrupted = save.info.current_win_corrupted;
+    }
+    // Games are counted once they start, from `start_new_game`.
+    let Some(save) = save.filter(|save| !save.finished) else {
+        return;
+    };
+    *game_info = save.info;
+    *setup = save.setup;
+    board.reset(save.board);
+    board.deselect();
+    *rng = GameRng::new(save.seed);
+    rng.board = save.rng;
+    move_log.history = save.history;
+}
+
+fn read_save() -> Option<SaveGame> {
+    let contents = storage::load(SAVE_KEY)?;
+    let save: SaveGame = match ron::from_str(&contents) {
+        Ok(save) => save,
+        Err(error) => {
+            warn!("Ignoring unreadable save: {error}");
+            return None;
+        }
+    };
+    if save.version != SAVE_VERSION {
+        warn!("Ignoring save of unsupported version {}", save.version);
+        return None;
+    }
+    let boards = [&save.board, &save.setup.start]
+        .into_iter()
+        .chain(save.history.snapshots().map(|snapshot| &snapshot.board));
+    for board in boards {
+        if let Err(error) = board.validate() {
+            warn!("Ignoring invalid save: {error}");
+            return None;
+        }
+    }
+    Some(save)
+}
+
+/// Saves the game whenever a move is recorded, undone or redone, and when
+/// it ends or restarts.
+fn autosave(
+    board: Res<Board>,
+    rng: Res<GameRng>,
+    move_log: Res<MoveLog>,
+    game_info: Res<GameInfo>,
+    setup: Res<GameSetup>,
+    state: Res<State<GameState>>,
+) {
+    // Menus don't change the game, and saving from them would lose whether
+    // it was finished.
+    if (!move_log.is_changed() && !state.is_changed()) || !state.get().in_game() {
+        return;
+    }
+    let save = SaveGame {
+        version: SAVE_VERSION,
+        finished: *state.get() != GameState::Playing,
+        seed: rng.seed,
+        rng: rng.board.clone(),
+        board: board.state.clone(),
+        history: move_log.history.clone(),
+        info: game_info.clone(),
+        setup: setup.clone(),
+    };
+    let result = ron::to_string(&save)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| {
+            storage::store(SAVE_KEY, &contents).map_err(|error| error.to_string())
+        });
+    if let Err(error) = result {
+        warn!("Failed to save the game: {error}");
+    }
+}
+
+/// Everything needed to resume a game where it was left.
+#[derive(Serialize, Deserialize)]
+struct SaveGame {
+    version: u32,
+    /// The game reached an ending, so the next launch starts a new board.
+    finished: bool,
+    seed: u64,
+    rng: ChaCha8Rng,
+    board: rules::Board,
+    history: History<ChaCha8Rng>,
+    info: GameInfo,
+    setup: GameSetup,
+}
@@ -0,0 +1,121 @@
+//! Points for merges.
+//!
+//! A score only depends on the merges of a game, so it can always be worked
+//! out again from the move log, undos included.
+
+use crate::rules::Merge;
+
+/// Points for a merge into a phase 1 smiler, doubled for each phase above.
+pub const BASE_POINTS: u32 = 10;
+/// Extra points for merging two normal smilers, in percent of the base.
+pub const CLEAN_BONUS_PERCENT: u32 = 50;
+/// Multiplier gained for each merge in a row without corruption, in percent.
+pub const STREAK_STEP_PERCENT: u32 = 25;
+pub const MAX_MULTIPLIER_PERCENT: u32 = 300;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub points: u32,
+    /// Merges in a row whose result came out normal.
+    pub streak: u32,
+}
+
+impl Score {
+    pub fn from_merges<'a>(merges: impl IntoIterator<Item = &'a Merge>) -> Self {
+        let mut score = Self::default();
+        for merge in merges {
+            score.add(merge);
+        }
+        score
+    }
+
+    /// Multiplier applied to the next merge, in percent.
+    pub fn multiplier_percent(&self) -> u32 {
+        (100 + self.streak * STREAK_STEP_PERCENT).min(MAX_MULTIPLIER_PERCENT)
+    }
+
+    /// Scores `merge` and returns the points it was worth. A mixed merge
+    /// that rolled corrupted costs its base points and ends the streak.
+    pub fn add(&mut self, merge: &Merge) -> i32 {
+        let phase = u32::from(merge.cell.phase.max(1));
+        let base = BASE_POINTS << (phase - 1).min(16);
+        let delta = if merge.cell.corrupted {
+            self.streak = 0;
+            if merge.mixed {
+                -(base as i32)
+            } else {
+                base as i32
+            }
+        } else {
+            let bonus = if merge.mixed {
+                0
+            } else {
+                base * CLEAN_BONUS_PERCENT / 100
+            };
+            let points = (base + bonus) * self.multiplier_percent() / 100;
+            self.streak += 1;
+            points as i32
+        };
+        self.points = self.points.saturating_add_signed(delta);
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Cell, Pos};
+
+    fn merge(phase: u8, corrupted: bool, mixed: bool) -> Merge {
+        Merge {
+            from: Pos::new(0, 0),
+            to: Pos::new(1, 0),
+            cell: Cell { phase, corrupted },
+            mixed,
+        }
+    }
+
+    #[test]
+    fn clean_merges_build_a_streak() {
+        let mut score = Score::default();
+        assert_eq!(score.add(&merge(1, false, false)), 15);
+        assert_eq!(score.add(&merge(1, false, false)), 18);
+        assert_eq!(score.add(&merge(2, false, false)), 45);
+        assert_eq!(score.points, 78);
+        assert_eq!(score.streak, 3);
+    }
+
+    #[test]
+    fn mixed_merges_get_no_bonus() {
+        let mut score = Score::default();
+        assert_eq!(score.add(&merge(1, false, true)), 10);
+        assert_eq!(score.streak, 1);
+    }
+
+    #[test]
+    fn corruption_ends_the_streak() {
+        let mut score = Score::default();
+        score.add(&merge(2, false, false));
+        assert_eq!(score.add(&merge(1, true, true)), -10);
+        assert_eq!(score.points, 20);
+        assert_eq!(score.streak, 0);
+        assert_eq!(score.add(&merge(1, true, false)), 10);
+        assert_eq!(score.streak, 0);
+    }
+
+    #[test]
+    fn points_never_go_negative() {
+        let mut score = Score::default();
+        score.add(&merge(3, true, true));
+        assert_eq!(score.points, 0);
+    }
+
+    #[test]
+    fn multiplier_is_capped() {
+        let score = Score {
+            points: 0,
+            streak: 100,
+        };
+        assert_eq!(score.multiplier_percent(), MAX_MULTIPLIER_PERCENT);
+    }
+}
@@ -0,0 +1,281 @@
+//! High scores: naming a new one once a game is over, and the tables of them.
+
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+use mergerration::{
+    highscores::{self, HighScores, Table},
+    profile::unix_time,
+    score::Score,
+};
+
+use crate::{
+    assets::{Campaign, CampaignHandle, CampaignLevels, Level},
+    game::{GameInfo, GameRng, GameSetup, MoveLog},
+    hud::SaveScoreButton,
+    in_game,
+    menu::{despawn_screen, spawn_menu_screen, MenuAction, MenuScreen},
+    recording::ReplayPlayer,
+    ui::{button_style, clicked, spawn_button},
+    GameState,
+};
+
+const MAX_NAME_LENGTH: usize = 12;
+
+pub struct ScoreboardPlugin;
+
+impl Plugin for ScoreboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScoreTable(HighScores::load().unwrap_or_else(|error| {
+            warn!("Starting new high scores, the stored ones are unreadable: {error}");
+            HighScores::default()
+        })))
+        .init_resource::<ScoreEntry>()
+        .insert_resource(ShownTable(0))
+        .add_systems(OnEnter(GameState::HighScores), show_first_table)
+        .add_systems(OnExit(GameState::HighScores), despawn_screen::<MenuScreen>)
+        .add_systems(
+            Update,
+            (
+                (
+                    offer_high_score.run_if(not(resource_exists::<ReplayPlayer>)),
+                    enter_name,
+                )
+                    .chain()
+                    .run_if(in_game),
+                spawn_high_scores.run_if(
+                    in_state(GameState::HighScores).and_then(resource_changed::<ShownTable>),
+                ),
+                save_high_scores,
+            ),
+        );
+    }
+}
+
+/// The high score table a game counts for.
+fn score_table(setup: &GameSetup) -> Table {
+    match &setup.level_id {
+        Some(id) => Table::Level(id.clone()),
+        None => Table::Mode(setup.mode),
+    }
+}
+
+/// Asks for a name once a game that made it into the high scores is over.
+fn offer_high_score(
+    state: Res<State<GameState>>,
+    setup: Res<GameSetup>,
+    rng: Res<GameRng>,
+    move_log: Res<MoveLog>,
+    high_scores: Res<HighScoreTable>,
+    mut game_info: ResMut<GameInfo>,
+    mut score_entry: ResMut<ScoreEntry>,
+) {
+    if !state.get().is_over() || game_info.score_offered {
+        return;
+    }
+    game_info.score_offered = true;
+    let table = score_table(&setup);
+    let score = Score::from_merges(move_log.merges());
+    if high_scores.qualifies(&table, score.points) {
+        let entry = highscores::Entry {
+            name: high_scores.last_name.clone(),
+            score: score.points,
+            moves: move_log.merges().len(),
+            seed: rng.seed,
+            time: unix_time(),
+            start: Some(setup.start.clone()),
+        };
+        score_entry.0 = Some((table, entry));
+    }
+}
+
+/// Typing edits the high score name, Enter or the SAVE SCORE button keep it.
+fn enter_name(
+    mut characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    save_button: Query<&RelativeCursorPosition, With<SaveScoreButton>>,
+    mut score_entry: ResMut<ScoreEntry>,
+    mut high_scores: ResMut<HighScoreTable>,
+) {
+    let Some((_, entry)) = &mut score_entry.0 else {
+        characters.clear();
+        return;
+    };
+    for character in characters.read() {
+        for char in character.char.chars() {
+            if !char.is_control() && entry.name.chars().count() < MAX_NAME_LENGTH {
+                entry.name.push(char);
+            }
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Backspace) {
+        entry.name.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Enter)
+        || clicked(save_button.single(), &mouse_button_input, &touches)
+    {
+        submit_score(&mut score_entry, &mut high_scores);
+    }
+}
+
+/// Records the pending high score, under "Player" if no name was typed.
+pub fn submit_score(score_entry: &mut ScoreEntry, high_scores: &mut HighScores) {
+    if let Some((table, mut entry)) = score_entry.0.take() {
+        entry.name = entry.name.trim().to_string();
+        if entry.name.is_empty() {
+            entry.name = "Player".to_string();
+        }
+        high_scores.insert(table, entry);
+    }
+}
+
+fn save_high_scores(high_scores: Res<HighScoreTable>) {
+    if high_scores.is_changed() && !high_scores.is_added() {
+        if let Err(error) = high_scores.save() {
+            warn!("Failed to save the high scores: {error}");
+        }
+    }
+}
+
+fn show_first_table(mut shown: ResMut<ShownTable>) {
+    shown.0 = 0;
+}
+
+fn table_name(
+    table: &Table,
+    campaign: Option<&Campaign>,
+    campaign_levels: Option<&CampaignLevels>,
+    levels: &Assets<Level>,
+) -> String {
+    match table {
+        Table::Mode(mode) => mode.name().to_string(),
+        Table::Level(id) => campaign
+            .and_then(|campaign| campaign.position(id))
+            .zip(campaign_levels)
+            .and_then(|(index, handles)| levels.get(&handles.0[index]))
+            .map_or(id.clone(), |level| level.name.clone()),
+    }
+}
+
+/// One table at a time, with its seeds as buttons to replay them.
+fn spawn_high_scores(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    screens: Query<Entity, With<MenuScreen>>,
+    shown: Res<ShownTable>,
+    high_scores: Res<HighScoreTable>,
+    campaign_handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+    campaign_levels: Option<Res<CampaignLevels>>,
+    levels: Res<Assets<Level>>,
+) {
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+    let text_style = |font_size| TextStyle {
+        font: asset_server.load("Marinda.ttf"),
+        font_size,
+        color: Color::WHITE,
+    };
+    let table = high_scores.tables.iter().nth(shown.0);
+    spawn_menu_screen(&mut commands, |parent| {
+        let Some((table, entries)) = table else {
+            parent.spawn(TextBundle::from_section(
+                "No high scores yet",
+                text_style(40.0),
+            ));
+            spawn_button(parent, &asset_server, "BACK", MenuAction::Back);
+            return;
+        };
+        let name = table_name(
+            table,
+            campaigns.get(&campaign_handle.0),
+            campaign_levels.as_deref(),
+            &levels,
+        );
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                spawn_button(parent, &asset_server, "<", MenuAction::PreviousTable).insert(Style {
+                    width: Val::Px(65.0),
+                    ..button_style(65.0)
+                });
+                parent.spawn(
+                    TextBundle::from_section(name, text_style(40.0))
+                        .with_style(Style {
+                            width: Val::Px(360.0),
+                            ..default()
+                        })
+                        .with_text_justify(JustifyText::Center),
+                );
+                spawn_button(parent, &asset_server, ">", MenuAction::NextTable).insert(Style {
+                    width: Val::Px(65.0),
+                    ..button_style(65.0)
+                });
+            });
+        for (rank, entry) in entries.iter().enumerate() {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(680.0),
+                        height: Val::Px(30.0),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    let row = format!(
+                        "{}. {}   {} points   {} moves   {}",
+                        rank + 1,
+                        entry.name,
+                        entry.score,
+                        entry.moves,
+                        highscores::date(entry.time),
+                    );
+                    parent.spawn(TextBundle::from_section(row, text_style(24.0)));
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::horizontal(Val::Px(10.0)),
+                                    border: UiRect::all(Val::Px(2.0)),
+                                    ..default()
+                                },
+                                border_color: BorderColor(Color::WHITE),
+                                background_color: BackgroundColor(Color::rgb(0.455, 0.643, 0.745)),
+                                ..default()
+                            },
+                            RelativeCursorPosition::default(),
+                            MenuAction::Replay(rank),
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                format!("Seed {}", entry.seed),
+                                text_style(24.0),
+                            ));
+                        });
+                });
+        }
+        spawn_button(parent, &asset_server, "BACK", MenuAction::Back);
+    });
+}
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct HighScoreTable(HighScores);
+
+/// High score of the game just over, while its name is being typed.
+#[derive(Resource, Default)]
+pub struct ScoreEntry(pub Option<(Table, highscores::Entry)>);
+
+/// Index of the high score table on screen.
+#[derive(Resource)]
+pub struct ShownTable(pub usize);
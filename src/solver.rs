@@ -0,0 +1,234 @@
+//! Monte Carlo solver: estimates, for every merge on a board, the chance of
+//! going on to build a normal winning smiler, by playing many games out
+//! from it against random refills and corruption rolls.
+//!
+//! The games are played by the hint's pick with some random moves mixed
+//! in, so the chances are what that player would get: a lower bound of
+//! what perfect play could do. Games follow the classic rules, where the
+//! first winning smiler ends the game.
+
+use rand::{seq::IteratorRandom, Rng};
+
+use crate::{
+    hint,
+    rules::{Board, Pos},
+};
+
+/// Merges a game is played out for when the board has no move limit.
+pub const DEFAULT_MAX_MOVES: usize = 100;
+/// Chance that a played out game picks a random merge over the hint's.
+const EXPLORATION: f64 = 0.1;
+
+/// What the games played out after one first merge came to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveStats {
+    pub from: Pos,
+    pub to: Pos,
+    pub games: u32,
+    pub clean_wins: u32,
+    pub corrupted_wins: u32,
+}
+
+impl MoveStats {
+    /// Estimated chance of a normal winning smiler after this merge.
+    pub fn clean_win_chance(&self) -> f64 {
+        self.chance(self.clean_wins)
+    }
+
+    /// Estimated chance of any winning smiler after this merge.
+    pub fn win_chance(&self) -> f64 {
+        self.chance(self.clean_wins + self.corrupted_wins)
+    }
+
+    fn chance(&self, wins: u32) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            f64::from(wins) / f64::from(self.games)
+        }
+    }
+}
+
+enum Outcome {
+    CleanWin,
+    CorruptedWin,
+    Lost,
+}
+
+/// A search on one board, refined by every call to `run`.
+#[derive(Clone, Debug)]
+pub struct Solver {
+    board: Board,
+    max_moves: usize,
+    moves: Vec<MoveStats>,
+    /// Index of the move to play the next game for.
+    next: usize,
+}
+
+impl Solver {
+    /// Starts a search on `board`, with `move_limit` merges left if the
+    /// game has a limit.
+    pub fn new(board: &Board, move_limit: Option<usize>) -> Self {
+        let mut board = board.clone();
+        board.deselect();
+        let moves = board
+            .merges()
+            .map(|(from, to)| MoveStats {
+                from,
+                to,
+                games: 0,
+                clean_wins: 0,
+                corrupted_wins: 0,
+            })
+            .collect();
+        Self {
+            board,
+            max_moves: move_limit.unwrap_or(DEFAULT_MAX_MOVES),
+            moves,
+            next: 0,
+        }
+    }
+
+    /// The board searched, without its selection.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Plays `games` more games, taking turns between the first merges.
+    pub fn run<R: Rng + ?Sized>(&mut self, games: usize, rng: &mut R) {
+        if self.moves.is_empty() {
+            return;
+        }
+        for _ in 0..games {
+            let index = self.next;
+            self.next = (self.next + 1) % self.moves.len();
+            let stats = &self.moves[index];
+            let outcome = self.play_out(stats.from, stats.to, rng);
+            let stats = &mut self.moves[index];
+            stats.games += 1;
+            match outcome {
+                Outcome::CleanWin => stats.clean_wins += 1,
+                Outcome::CorruptedWin => stats.corrupted_wins += 1,
+                Outcome::Lost => {}
+            }
+        }
+    }
+
+    /// Every legal first merge with its games so far.
+    pub fn moves(&self) -> &[MoveStats] {
+        &self.moves
+    }
+
+    /// Games played so far, over all first merges.
+    pub fn games(&self) -> u32 {
+        self.moves.iter().map(|stats| stats.games).sum()
+    }
+
+    /// The merge with the best chance of a normal winning smiler, any
+    /// winning smiler breaking ties.
+    pub fn best(&self) -> Option<&MoveStats> {
+        self.moves.iter().max_by(|a, b| {
+            a.clean_win_chance()
+                .total_cmp(&b.clean_win_chance())
+                .then(a.win_chance().total_cmp(&b.win_chance()))
+        })
+    }
+
+    /// Estimated chance of a normal winning smiler from the board, playing
+    /// the best merge.
+    pub fn clean_win_chance(&self) -> f64 {
+        self.best().map_or(0.0, MoveStats::clean_win_chance)
+    }
+
+    fn play_out<R: Rng + ?Sized>(&self, from: Pos, to: Pos, rng: &mut R) -> Outcome {
+        let mut board = self.board.clone();
+        let (mut from, mut to) = (from, to);
+        for _ in 0..self.max_moves {
+            let Some(merge) = board.merge(from, to, rng) else {
+                break;
+            };
+            if board.wins(&merge) {
+                return if merge.cell.corrupted {
+                    Outcome::CorruptedWin
+                } else {
+                    Outcome::CleanWin
+                };
+            }
+            board.apply_gravity();
+            board.refill(rng);
+            let next = if rng.gen_bool(EXPLORATION) {
+                board.merges().choose(rng)
+            } else {
+                hint::suggest(&board)
+            };
+            let Some(next) = next else {
+                break;
+            };
+            (from, to) = next;
+        }
+        Outcome::Lost
+    }
+}
+
+/// Searches `board` with `games` games for each first merge.
+pub fn solve<R: Rng + ?Sized>(
+    board: &Board,
+    move_limit: Option<usize>,
+    games: usize,
+    rng: &mut R,
+) -> Solver {
+    let mut solver = Solver::new(board, move_limit);
+    solver.run(games * solver.moves.len(), rng);
+    solver
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::rules::{Cell, Shape};
+
+    /// Two smilers side by side, one merge from winning.
+    fn one_move_from_winning(corrupted: bool) -> Board {
+        let mut board = Board::empty(Shape::rect(2, 1));
+        let cell = Cell {
+            phase: 4,
+            corrupted,
+        };
+        board.set(Pos::new(0, 0), Some(cell));
+        board.set(Pos::new(1, 0), Some(cell));
+        board
+    }
+
+    #[test]
+    fn solve_finds_the_winning_merge() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let solver = solve(&one_move_from_winning(false), None, 10, &mut rng);
+        assert_eq!(solver.moves().len(), 2);
+        assert_eq!(solver.games(), 20);
+        let best = solver.best().unwrap();
+        assert_eq!(best.clean_wins, 10);
+        assert_eq!(solver.clean_win_chance(), 1.0);
+    }
+
+    #[test]
+    fn solve_tells_corrupted_wins_apart() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let solver = solve(&one_move_from_winning(true), None, 10, &mut rng);
+        let best = solver.best().unwrap();
+        assert_eq!(best.clean_win_chance(), 0.0);
+        assert_eq!(best.win_chance(), 1.0);
+    }
+
+    #[test]
+    fn solve_without_merges() {
+        let mut board = one_move_from_winning(false);
+        board.set(Pos::new(1, 0), Some(Cell::default()));
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let solver = solve(&board, None, 10, &mut rng);
+        assert!(solver.best().is_none());
+        assert_eq!(solver.clean_win_chance(), 0.0);
+    }
+}
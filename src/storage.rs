@@ -0,0 +1,88 @@
+//! Small key/value persistence: one file per key in the user's data
+//! directory on desktop, browser local storage on the web.
+
+use std::io;
+
+use serde::de::DeserializeOwned;
+
+/// Reads the RON value stored under `key`, or the default if there's none.
+/// One that can't be read is copied to `<key>.unreadable` first, so that
+/// storing a new value doesn't lose it, and the error says why.
+pub fn load_ron<T: DeserializeOwned + Default>(key: &str) -> Result<T, String> {
+    let Some(contents) = load(key) else {
+        return Ok(T::default());
+    };
+    ron::from_str(&contents).map_err(|error| {
+        let backup = format!("{key}.unreadable");
+        match store(&backup, &contents) {
+            Ok(()) => format!("{error}, kept it as {backup}"),
+            Err(store_error) => format!("{error}, and couldn't keep a copy: {store_error}"),
+        }
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(key: &str) -> std::path::PathBuf {
+    dirs::data_dir()
+        .unwrap_or_default()
+        .join("mergerration")
+        .join(format!("{key}.ron"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(path(key)).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn store(key: &str, contents: &str) -> io::Result<()> {
+    let path = path(key);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn remove(key: &str) -> io::Result<()> {
+    match std::fs::remove_file(path(key)) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> io::Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "no local storage"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn storage_key(key: &str) -> String {
+    format!("mergerration.{key}")
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(error: web_sys::wasm_bindgen::JsValue) -> io::Error {
+    io::Error::other(format!("{error:?}"))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(key: &str) -> Option<String> {
+    local_storage().ok()?.get_item(&storage_key(key)).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn store(key: &str, contents: &str) -> io::Result<()> {
+    local_storage()?
+        .set_item(&storage_key(key), contents)
+        .map_err(js_error)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn remove(key: &str) -> io::Result<()> {
+    local_storage()?
+        .remove_item(&storage_key(key))
+        .map_err(js_error)
+}
@@ -0,0 +1,113 @@
+//! Widgets shared by the game screen and the menus: buttons and toasts.
+
+use bevy::{ecs::system::EntityCommands, prelude::*, ui::RelativeCursorPosition};
+
+const TOAST_SECONDS: f32 = 3.0;
+
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_toasts);
+    }
+}
+
+pub fn button_style(height: f32) -> Style {
+    Style {
+        width: Val::Px(200.0),
+        height: Val::Px(height),
+        border: UiRect::all(Val::Px(5.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+pub fn spawn_button<'a>(
+    parent: &'a mut ChildBuilder,
+    asset_server: &AssetServer,
+    label: &str,
+    marker: impl Component,
+) -> EntityCommands<'a> {
+    let mut button = parent.spawn((
+        ButtonBundle {
+            style: button_style(65.0),
+            border_color: BorderColor(Color::WHITE),
+            background_color: BackgroundColor(Color::rgb(0.455, 0.643, 0.745)),
+            ..default()
+        },
+        RelativeCursorPosition::default(),
+        marker,
+    ));
+    button.with_children(|parent| {
+        parent.spawn(
+            TextBundle::from_section(
+                label,
+                TextStyle {
+                    font: asset_server.load("Marinda.ttf"),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_text_justify(JustifyText::Center),
+        );
+    });
+    button
+}
+
+pub fn clicked(
+    button: &RelativeCursorPosition,
+    mouse_button_input: &ButtonInput<MouseButton>,
+    touches: &Touches,
+) -> bool {
+    (mouse_button_input.just_pressed(MouseButton::Left) || touches.any_just_pressed())
+        && button.mouse_over()
+}
+
+pub fn show(style: &mut Style, visible: bool) {
+    let display = if visible {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    if style.display != display {
+        style.display = display;
+    }
+}
+
+pub fn spawn_toast(
+    commands: &mut Commands,
+    toast_area: Entity,
+    asset_server: &AssetServer,
+    message: String,
+) {
+    commands.entity(toast_area).with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section(
+                message,
+                TextStyle {
+                    font: asset_server.load("Marinda.ttf"),
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_background_color(Color::rgb(0.455, 0.643, 0.745)),
+            Toast(Timer::from_seconds(TOAST_SECONDS, TimerMode::Once)),
+        ));
+    });
+}
+
+fn update_toasts(mut commands: Commands, time: Res<Time>, mut query: Query<(Entity, &mut Toast)>) {
+    for (entity, mut toast) in &mut query {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ToastArea;
+
+/// Notification that disappears once its timer runs out.
+#[derive(Component)]
+struct Toast(Timer);